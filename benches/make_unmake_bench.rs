@@ -0,0 +1,68 @@
+//! Times in-place make/unmake (with UndoRecords) on GameState, for each kind of move.
+//! Run with `cargo bench`.
+use std::hint::black_box;
+use std::time::{ Duration, Instant };
+use bongcloud::game_state::GameState;
+use bongcloud::game_move::{ GameMove, MoveType };
+use bongcloud::square::Square;
+use bongcloud::fen::parse_fen;
+
+
+/// Number of times each position's move is made and unmade per run.
+const ITERATIONS: u32 = 1_000_000;
+
+
+/// Positions and a move to make on each, covering each kind of move.
+fn bench_cases() -> Vec<(GameState, GameMove)> {
+    vec![
+        (
+            parse_fen("rnbqkbnr/ppp1pppp/8/3p4/8/4PN2/PPPP2PP/RNBQK2R w KQkq - 10 6"),
+            GameMove::new(Square::E1, Square::G1, MoveType::KingCastle),
+        ),
+        (
+            parse_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 5 30"),
+            GameMove::new(Square::D1, Square::H5, MoveType::Quiet),
+        ),
+        (
+            parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 10 6"),
+            GameMove::new(Square::E2, Square::E4, MoveType::DoublePawnPush),
+        ),
+        (
+            parse_fen("rnbqkbnr/pppp1ppp/8/4p3/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 10 6"),
+            GameMove::new(Square::F3, Square::E5, MoveType::Capture),
+        ),
+        (
+            parse_fen("rnbqkbnr/ppp2ppp/8/3pP3/8/8/PPPP1PPP/RNBQKB1R w KQkq d6 0 2"),
+            GameMove::new(Square::E5, Square::D6, MoveType::EpCapture),
+        ),
+        (
+            parse_fen("rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 0 3"),
+            GameMove::new(Square::F7, Square::E8, MoveType::QueenPromoCapture),
+        ),
+    ]
+}
+
+
+/// Make and unmake each move in-place.
+fn bench_make_unmake(cases: &[(GameState, GameMove)]) -> Duration {
+    let start = Instant::now();
+    for (state, game_move) in cases {
+        let mut state = *state;
+        for _ in 0..ITERATIONS {
+            let undo = state.make(black_box(*game_move));
+            black_box(&state);
+            state.unmake(undo);
+        }
+        black_box(&state);
+    }
+    start.elapsed()
+}
+
+
+fn main() {
+    let cases = bench_cases();
+    let total = ITERATIONS as f64 * cases.len() as f64;
+
+    let in_place = bench_make_unmake(&cases);
+    println!("make/unmake: {:>8.2} ns/move", in_place.as_nanos() as f64 / total);
+}
@@ -0,0 +1,65 @@
+//! This file contains the make_book tool, which builds a Polyglot opening book from PGN files.
+//!
+//! Usage: make_book [--max-ply N] [--min-count N] [--min-elo N] <book.bin> <games.pgn>...
+use std::env;
+use std::fs;
+use std::process;
+use bongcloud::pgn;
+use bongcloud::polyglot::builder::{ BookBuilder, BookOptions };
+
+
+const USAGE: &str =
+    "Usage: make_book [--max-ply N] [--min-count N] [--min-elo N] <book.bin> <games.pgn>...";
+
+
+pub fn main() {
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+/// Build the book described by the given command-line arguments.
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut options = BookOptions::default();
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .and_then(|value| value.parse::<u32>().ok())
+                .ok_or_else(|| format!("{} needs a number.\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--max-ply" => options.max_ply = value()? as usize,
+            "--min-count" => options.min_count = value()?,
+            "--min-elo" => options.min_elo = Some(value()?),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}.\n{}", arg, USAGE)),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() < 2 { return Err(USAGE.to_string()) }
+    let book_path = paths.remove(0);
+
+    let mut builder = BookBuilder::new(options);
+    let (mut read, mut used) = (0, 0);
+    for path in &paths {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        for game in pgn::parse_pgn(&text).map_err(|e| format!("{}: {}", path, e))? {
+            read += 1;
+            // A broken game shouldn't stop the rest of a large collection from being read.
+            match builder.add_game(&game) {
+                Ok(true) => used += 1,
+                Ok(false) => {},
+                Err(err) => eprintln!("{}: skipping game {}: {}", path, read, err),
+            }
+        }
+    }
+
+    builder.write(&book_path)?;
+    println!(
+        "Used {} of {} games; wrote {} entries to {}.",
+        used, read, builder.entries().len(), book_path,
+    );
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! This file contains the Bitboard type: a set of squares stored as the bits of a u64.
+use std::fmt;
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr,
+};
+use crate::bits::masks;
+use crate::square::{ Square, File, Rank };
+
+
+/// A Bitboard is a 64-bit unsigned integer which gives piece occupancy, with bit i set iff square
+/// i is in the set. See chessprogrammingwiki page for more details.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+
+/// A compass direction on the board, with north being towards the 8th rank.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl Direction {
+    /// Get all eight directions.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+
+    /// Get the index of this direction in `Direction::ALL`.
+    pub const fn idx(self) -> usize {
+        self as usize
+    }
+
+    /// Get the direction pointing the other way.
+    pub const fn opposite(self) -> Self {
+        match self {
+            Direction::North        => Direction::South,
+            Direction::South        => Direction::North,
+            Direction::East         => Direction::West,
+            Direction::West         => Direction::East,
+            Direction::NorthEast    => Direction::SouthWest,
+            Direction::NorthWest    => Direction::SouthEast,
+            Direction::SouthEast    => Direction::NorthWest,
+            Direction::SouthWest    => Direction::NorthEast,
+        }
+    }
+
+    /// Get the (file, rank) step taken by moving one square in this direction.
+    pub const fn delta(self) -> (i8, i8) {
+        match self {
+            Direction::North        => (0, 1),
+            Direction::South        => (0, -1),
+            Direction::East         => (1, 0),
+            Direction::West         => (-1, 0),
+            Direction::NorthEast    => (1, 1),
+            Direction::NorthWest    => (-1, 1),
+            Direction::SouthEast    => (1, -1),
+            Direction::SouthWest    => (-1, -1),
+        }
+    }
+}
+
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const FULL: Bitboard = Bitboard(u64::MAX);
+
+    /// Get a Bitboard with only the given square set.
+    pub const fn from_square(sq: Square) -> Self {
+        Bitboard(1 << sq.as_u8())
+    }
+
+    /// Returns whether no squares are set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns whether the given square is set.
+    pub const fn contains(self, sq: Square) -> bool {
+        self.0 & (1 << sq.as_u8()) != 0
+    }
+
+    /// Get the number of set squares.
+    pub const fn popcount(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Get the least significant set square, or None if the Bitboard is empty.
+    pub fn lsb(self) -> Option<Square> {
+        if self.is_empty() { None } else { Some(Square::new(self.0.trailing_zeros() as u8)) }
+    }
+
+    /// Get the most significant set square, or None if the Bitboard is empty.
+    pub fn msb(self) -> Option<Square> {
+        if self.is_empty() { None } else { Some(Square::new(63 - self.0.leading_zeros() as u8)) }
+    }
+
+    /// Remove and return the least significant set square, or None if the Bitboard is empty.
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let lsb = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(lsb)
+    }
+
+    /// Shift every square one step in the given direction. Squares shifted off the board are
+    /// dropped rather than wrapping around to the other side.
+    pub const fn shift(self, dir: Direction) -> Self {
+        let bb = self.0;
+        let not_a = !masks::FILE_A.0;
+        let not_h = !masks::FILE_H.0;
+        Bitboard(match dir {
+            Direction::North        => bb << 8,
+            Direction::South        => bb >> 8,
+            Direction::East         => (bb & not_h) << 1,
+            Direction::West         => (bb & not_a) >> 1,
+            Direction::NorthEast    => (bb & not_h) << 9,
+            Direction::NorthWest    => (bb & not_a) << 7,
+            Direction::SouthEast    => (bb & not_h) >> 7,
+            Direction::SouthWest    => (bb & not_a) >> 9,
+        })
+    }
+
+    /// Shift every square one rank towards the 8th rank.
+    pub const fn north(self) -> Self {
+        self.shift(Direction::North)
+    }
+
+    /// Shift every square one rank towards the 1st rank.
+    pub const fn south(self) -> Self {
+        self.shift(Direction::South)
+    }
+
+    /// Shift every square one file towards the h-file.
+    pub const fn east(self) -> Self {
+        self.shift(Direction::East)
+    }
+
+    /// Shift every square one file towards the a-file.
+    pub const fn west(self) -> Self {
+        self.shift(Direction::West)
+    }
+
+    /// Shift every square one square diagonally towards h8.
+    pub const fn north_east(self) -> Self {
+        self.shift(Direction::NorthEast)
+    }
+
+    /// Shift every square one square diagonally towards a8.
+    pub const fn north_west(self) -> Self {
+        self.shift(Direction::NorthWest)
+    }
+
+    /// Shift every square one square diagonally towards h1.
+    pub const fn south_east(self) -> Self {
+        self.shift(Direction::SouthEast)
+    }
+
+    /// Shift every square one square diagonally towards a1.
+    pub const fn south_west(self) -> Self {
+        self.shift(Direction::SouthWest)
+    }
+}
+
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+
+    fn bitand(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+
+    fn bitor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Bitboard;
+
+    fn bitxor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ rhs.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Bitboard) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Bitboard) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Bitboard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+/// Raw shift of the underlying bits; squares may wrap across files. Prefer `shift` for moving
+/// squares in a direction.
+impl Shl<u32> for Bitboard {
+    type Output = Bitboard;
+
+    fn shl(self, rhs: u32) -> Bitboard {
+        Bitboard(self.0 << rhs)
+    }
+}
+
+/// Raw shift of the underlying bits; squares may wrap across files. Prefer `shift` for moving
+/// squares in a direction.
+impl Shr<u32> for Bitboard {
+    type Output = Bitboard;
+
+    fn shr(self, rhs: u32) -> Bitboard {
+        Bitboard(self.0 >> rhs)
+    }
+}
+
+
+/// An iterator over the set squares of a Bitboard, from lsb to msb.
+pub struct BitboardIter {
+    bb: Bitboard,
+}
+
+impl Iterator for BitboardIter {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        self.bb.pop_lsb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.bb.popcount() as usize;
+        (count, Some(count))
+    }
+}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+
+    fn into_iter(self) -> BitboardIter {
+        BitboardIter { bb: self }
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Self {
+        iter.into_iter().fold(Bitboard::EMPTY, |bb, sq| bb | Bitboard::from_square(sq))
+    }
+}
+
+
+impl fmt::Display for Bitboard {
+    /// Draw the Bitboard as an 8x8 grid from white's point of view, with rank and file labels.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rank in Rank::all().rev() {
+            write!(f, "{}", rank)?;
+            for file in File::all() {
+                let set = self.contains(Square::from_file_rank(file, rank));
+                write!(f, " {}", if set { 'X' } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, " ")?;
+        for file in File::all() {
+            write!(f, " {}", file)?;
+        }
+        writeln!(f)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_queries() {
+        let bb: Bitboard = [Square::A1, Square::E4, Square::H8].into_iter().collect();
+        assert_eq!(bb.popcount(), 3);
+        assert!(bb.contains(Square::E4));
+        assert!(!bb.contains(Square::E5));
+        assert_eq!(bb.lsb(), Some(Square::A1));
+        assert_eq!(bb.msb(), Some(Square::H8));
+        assert_eq!(bb.into_iter().collect::<Vec<_>>(), vec![Square::A1, Square::E4, Square::H8]);
+        assert_eq!(Bitboard::EMPTY.lsb(), None);
+        assert_eq!(Bitboard::EMPTY.msb(), None);
+
+        let mut bb = bb;
+        assert_eq!(bb.pop_lsb(), Some(Square::A1));
+        assert_eq!(bb.popcount(), 2);
+    }
+
+    #[test]
+    fn test_shifts_do_not_wrap() {
+        let a_file = masks::FILE_A;
+        let h_file = masks::FILE_H;
+        assert_eq!(a_file.west(), Bitboard::EMPTY);
+        assert_eq!(h_file.east(), Bitboard::EMPTY);
+        assert_eq!(h_file.north_east(), Bitboard::EMPTY);
+        assert_eq!(a_file.south_west(), Bitboard::EMPTY);
+        assert_eq!(Bitboard::from_square(Square::H8).north(), Bitboard::EMPTY);
+        assert_eq!(Bitboard::from_square(Square::A1).south(), Bitboard::EMPTY);
+
+        let e4 = Bitboard::from_square(Square::E4);
+        for dir in Direction::ALL {
+            let (file_delta, rank_delta) = dir.delta();
+            let expected_sq = Square::E4.offset(file_delta, rank_delta).unwrap();
+            assert_eq!(e4.shift(dir), Bitboard::from_square(expected_sq));
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let bb = Bitboard::from_square(Square::A1) | Bitboard::from_square(Square::H8);
+        let expected = "\
+8 . . . . . . . X
+7 . . . . . . . .
+6 . . . . . . . .
+5 . . . . . . . .
+4 . . . . . . . .
+3 . . . . . . . .
+2 . . . . . . . .
+1 X . . . . . . .
+  a b c d e f g h
+";
+        assert_eq!(bb.to_string(), expected);
+    }
+}
@@ -1,77 +1,253 @@
-///! This file contains various useful bitmasks for bitboards.
-use crate::game_state::{ BitBoard, Square };
-
-
-// Bit representations for ranks.
-pub const RANK_1: BitBoard = 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_11111111;
-pub const RANK_2: BitBoard = 0b00000000_00000000_00000000_00000000_00000000_00000000_11111111_00000000;
-pub const RANK_3: BitBoard = 0b00000000_00000000_00000000_00000000_00000000_11111111_00000000_00000000;
-pub const RANK_4: BitBoard = 0b00000000_00000000_00000000_00000000_11111111_00000000_00000000_00000000;
-pub const RANK_5: BitBoard = 0b00000000_00000000_00000000_11111111_00000000_00000000_00000000_00000000;
-pub const RANK_6: BitBoard = 0b00000000_00000000_11111111_00000000_00000000_00000000_00000000_00000000;
-pub const RANK_7: BitBoard = 0b00000000_11111111_00000000_00000000_00000000_00000000_00000000_00000000;
-pub const RANK_8: BitBoard = 0b11111111_00000000_00000000_00000000_00000000_00000000_00000000_00000000;
-
-// Bit representations for files.
-pub const FILE_A: BitBoard = 0b00000001_00000001_00000001_00000001_00000001_00000001_00000001_00000001;
-pub const FILE_B: BitBoard = 0b00000010_00000010_00000010_00000010_00000010_00000010_00000010_00000010;
-pub const FILE_C: BitBoard = 0b00000100_00000100_00000100_00000100_00000100_00000100_00000100_00000100;
-pub const FILE_D: BitBoard = 0b00001000_00001000_00001000_00001000_00001000_00001000_00001000_00001000;
-pub const FILE_E: BitBoard = 0b00010000_00010000_00010000_00010000_00010000_00010000_00010000_00010000;
-pub const FILE_F: BitBoard = 0b00100000_00100000_00100000_00100000_00100000_00100000_00100000_00100000;
-pub const FILE_G: BitBoard = 0b01000000_01000000_01000000_01000000_01000000_01000000_01000000_01000000;
-pub const FILE_H: BitBoard = 0b10000000_10000000_10000000_10000000_10000000_10000000_10000000_10000000;
-
-// Bit representations for squares.
-pub const SQUARES: [BitBoard; 64] = make_square_masks();
+//! This file contains various useful bitmasks for bitboards.
+use crate::bits::bitboard::{ Bitboard, Direction };
+use crate::game_state::Color;
+
+
+// Bitboards for ranks.
+pub const RANK_1: Bitboard = Bitboard(0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_11111111);
+pub const RANK_2: Bitboard = Bitboard(0b00000000_00000000_00000000_00000000_00000000_00000000_11111111_00000000);
+pub const RANK_3: Bitboard = Bitboard(0b00000000_00000000_00000000_00000000_00000000_11111111_00000000_00000000);
+pub const RANK_4: Bitboard = Bitboard(0b00000000_00000000_00000000_00000000_11111111_00000000_00000000_00000000);
+pub const RANK_5: Bitboard = Bitboard(0b00000000_00000000_00000000_11111111_00000000_00000000_00000000_00000000);
+pub const RANK_6: Bitboard = Bitboard(0b00000000_00000000_11111111_00000000_00000000_00000000_00000000_00000000);
+pub const RANK_7: Bitboard = Bitboard(0b00000000_11111111_00000000_00000000_00000000_00000000_00000000_00000000);
+pub const RANK_8: Bitboard = Bitboard(0b11111111_00000000_00000000_00000000_00000000_00000000_00000000_00000000);
+
+// Bitboards for files.
+pub const FILE_A: Bitboard = Bitboard(0b00000001_00000001_00000001_00000001_00000001_00000001_00000001_00000001);
+pub const FILE_B: Bitboard = Bitboard(0b00000010_00000010_00000010_00000010_00000010_00000010_00000010_00000010);
+pub const FILE_C: Bitboard = Bitboard(0b00000100_00000100_00000100_00000100_00000100_00000100_00000100_00000100);
+pub const FILE_D: Bitboard = Bitboard(0b00001000_00001000_00001000_00001000_00001000_00001000_00001000_00001000);
+pub const FILE_E: Bitboard = Bitboard(0b00010000_00010000_00010000_00010000_00010000_00010000_00010000_00010000);
+pub const FILE_F: Bitboard = Bitboard(0b00100000_00100000_00100000_00100000_00100000_00100000_00100000_00100000);
+pub const FILE_G: Bitboard = Bitboard(0b01000000_01000000_01000000_01000000_01000000_01000000_01000000_01000000);
+pub const FILE_H: Bitboard = Bitboard(0b10000000_10000000_10000000_10000000_10000000_10000000_10000000_10000000);
+
+// Rank and file masks indexed by rank or file.
+pub const RANKS: [Bitboard; 8] = [RANK_1, RANK_2, RANK_3, RANK_4, RANK_5, RANK_6, RANK_7, RANK_8];
+pub const FILES: [Bitboard; 8] = [FILE_A, FILE_B, FILE_C, FILE_D, FILE_E, FILE_F, FILE_G, FILE_H];
+
+// Bitboards for squares.
+pub const SQUARES: [Bitboard; 64] = make_square_masks();
 
 // Knight move masks.
-pub const KNIGHT_MOVES: [BitBoard; 64] = make_knight_move_masks();
+pub const KNIGHT_MOVES: [Bitboard; 64] = make_knight_move_masks();
+
+// King move masks.
+pub const KING_MOVES: [Bitboard; 64] = make_king_move_masks();
+
+// Pawn attack masks, indexed by [color][square].
+pub const PAWN_ATTACKS: [[Bitboard; 64]; 2] = make_pawn_attack_masks();
+
+// Rays from a square to the board edge, not including the square, indexed by [direction][square].
+pub const RAYS: [[Bitboard; 64]; 8] = make_ray_masks();
+
+// Squares strictly between two squares on a shared rank, file or diagonal, indexed by
+// [square][square]. Empty if the squares are not aligned.
+pub static BETWEEN: [[Bitboard; 64]; 64] = make_between_masks();
+
+// The full rank, file or diagonal through two squares, indexed by [square][square]. Empty if the
+// squares are not aligned.
+pub static LINE: [[Bitboard; 64]; 64] = make_line_masks();
+
+// Files to either side of each file, indexed by file.
+pub const ADJACENT_FILES: [Bitboard; 8] = make_adjacent_file_masks();
+
+// Squares in front of a pawn on its own and adjacent files, indexed by [color][square]. A pawn is
+// passed if no enemy pawns are in its span.
+pub const PASSED_PAWN_SPANS: [[Bitboard; 64]; 2] = make_passed_pawn_span_masks();
+
+// Squares around a king used for king safety, indexed by [color][square]: the king's square, its
+// neighbours, and the three squares two ranks in front of it.
+pub const KING_ZONES: [[Bitboard; 64]; 2] = make_king_zone_masks();
 
 
 // Make masks with bit set for each square.
-const fn make_square_masks() -> [BitBoard; 64] {
-    let mut squares = [0; 64];
+const fn make_square_masks() -> [Bitboard; 64] {
+    let mut squares = [Bitboard::EMPTY; 64];
     let mut i = 0;
     while i < 64 {
-        squares[i] = 1 << i;
+        squares[i] = Bitboard(1 << i);
         i += 1;
     }
     squares
 }
 
 /// Make masks for knight moves.
-const fn make_knight_move_masks() -> [BitBoard; 64] {
-    let mut masks = [0; 64];
-    let mut i: Square = 0;
+const fn make_knight_move_masks() -> [Bitboard; 64] {
+    let mut masks = [Bitboard::EMPTY; 64];
+    let mut i = 0;
+    while i < 64 {
+        masks[i] = make_knight_mask(i);
+        i += 1;
+    }
+    masks
+}
+
+/// Make mask for knight move from a square index.
+const fn make_knight_mask(sq: usize) -> Bitboard {
+    let mut mask = 0;
+    let knight_sq = SQUARES[sq].0;
+
+    mask |= knight_sq << 17 & !FILE_A.0;
+    mask |= knight_sq << 10 & !(FILE_A.0 | FILE_B.0);
+    mask |= knight_sq >>  6 & !(FILE_A.0 | FILE_B.0);
+    mask |= knight_sq >> 15 & !FILE_A.0;
+    mask |= knight_sq << 15 & !FILE_H.0;
+    mask |= knight_sq <<  6 & !(FILE_H.0 | FILE_G.0);
+    mask |= knight_sq >> 10 & !(FILE_H.0 | FILE_G.0);
+    mask |= knight_sq >> 17 & !FILE_H.0;
+
+    Bitboard(mask)
+}
+
+/// Make masks for king moves.
+const fn make_king_move_masks() -> [Bitboard; 64] {
+    let mut masks = [Bitboard::EMPTY; 64];
+    let mut i = 0;
+    while i < 64 {
+        let mut mask = 0;
+        let mut d = 0;
+        while d < 8 {
+            mask |= SQUARES[i].shift(Direction::ALL[d]).0;
+            d += 1;
+        }
+        masks[i] = Bitboard(mask);
+        i += 1;
+    }
+    masks
+}
+
+/// Make masks for pawn attacks for both colors.
+const fn make_pawn_attack_masks() -> [[Bitboard; 64]; 2] {
+    let mut masks = [[Bitboard::EMPTY; 64]; 2];
+    let mut i = 0;
     while i < 64 {
-        masks[i as usize] = make_knight_mask(i);
+        let sq = SQUARES[i];
+        masks[Color::White.idx()][i] = Bitboard(sq.north_east().0 | sq.north_west().0);
+        masks[Color::Black.idx()][i] = Bitboard(sq.south_east().0 | sq.south_west().0);
         i += 1;
     }
     masks
 }
 
-/// Make mask for knight move from a square.
-const fn make_knight_mask(sq: Square) -> BitBoard {
+/// Make masks for rays in each direction.
+const fn make_ray_masks() -> [[Bitboard; 64]; 8] {
+    let mut masks = [[Bitboard::EMPTY; 64]; 8];
+    let mut d = 0;
+    while d < 8 {
+        let mut i = 0;
+        while i < 64 {
+            masks[d][i] = make_ray_mask(i, Direction::ALL[d]);
+            i += 1;
+        }
+        d += 1;
+    }
+    masks
+}
+
+/// Make mask for a ray from a square index to the board edge in the given direction.
+const fn make_ray_mask(sq: usize, dir: Direction) -> Bitboard {
     let mut mask = 0;
-    let knight_sq = SQUARES[sq as usize];
-
-    mask |= knight_sq << 17 & !FILE_A;
-    mask |= knight_sq << 10 & !(FILE_A | FILE_B);
-    mask |= knight_sq >>  6 & !(FILE_A | FILE_B);
-    mask |= knight_sq >> 15 & !FILE_A;
-    mask |= knight_sq << 15 & !FILE_H;
-    mask |= knight_sq <<  6 & !(FILE_H | FILE_G);
-    mask |= knight_sq >> 10 & !(FILE_H | FILE_G);
-    mask |= knight_sq >> 17 & !FILE_H;
-
-    mask
+    let mut cur = SQUARES[sq].shift(dir);
+    while !cur.is_empty() {
+        mask |= cur.0;
+        cur = cur.shift(dir);
+    }
+    Bitboard(mask)
+}
+
+/// Make masks for the squares between each pair of squares.
+const fn make_between_masks() -> [[Bitboard; 64]; 64] {
+    let mut masks = [[Bitboard::EMPTY; 64]; 64];
+    let mut from = 0;
+    while from < 64 {
+        let mut d = 0;
+        while d < 8 {
+            let dir = Direction::ALL[d];
+            let ray = RAYS[dir.idx()][from].0;
+            let mut to = 0;
+            while to < 64 {
+                if ray & SQUARES[to].0 != 0 {
+                    masks[from][to] = Bitboard(ray & RAYS[dir.opposite().idx()][to].0);
+                }
+                to += 1;
+            }
+            d += 1;
+        }
+        from += 1;
+    }
+    masks
+}
+
+/// Make masks for the line through each pair of squares.
+const fn make_line_masks() -> [[Bitboard; 64]; 64] {
+    let mut masks = [[Bitboard::EMPTY; 64]; 64];
+    let mut from = 0;
+    while from < 64 {
+        let mut d = 0;
+        while d < 8 {
+            let dir = Direction::ALL[d];
+            let ray = RAYS[dir.idx()][from].0;
+            let line = ray | RAYS[dir.opposite().idx()][from].0 | SQUARES[from].0;
+            let mut to = 0;
+            while to < 64 {
+                if ray & SQUARES[to].0 != 0 {
+                    masks[from][to] = Bitboard(line);
+                }
+                to += 1;
+            }
+            d += 1;
+        }
+        from += 1;
+    }
+    masks
+}
+
+/// Make masks for the files adjacent to each file.
+const fn make_adjacent_file_masks() -> [Bitboard; 8] {
+    let mut masks = [Bitboard::EMPTY; 8];
+    let mut f = 0;
+    while f < 8 {
+        let file = Bitboard(FILE_A.0 << f);
+        masks[f] = Bitboard(file.east().0 | file.west().0);
+        f += 1;
+    }
+    masks
+}
+
+/// Make masks for passed pawn spans for both colors.
+const fn make_passed_pawn_span_masks() -> [[Bitboard; 64]; 2] {
+    let mut masks = [[Bitboard::EMPTY; 64]; 2];
+    let mut i = 0;
+    while i < 64 {
+        let north = RAYS[Direction::North.idx()][i];
+        let south = RAYS[Direction::South.idx()][i];
+        masks[Color::White.idx()][i] = Bitboard(north.0 | north.east().0 | north.west().0);
+        masks[Color::Black.idx()][i] = Bitboard(south.0 | south.east().0 | south.west().0);
+        i += 1;
+    }
+    masks
+}
+
+/// Make masks for king zones for both colors.
+const fn make_king_zone_masks() -> [[Bitboard; 64]; 2] {
+    let mut masks = [[Bitboard::EMPTY; 64]; 2];
+    let mut i = 0;
+    while i < 64 {
+        let around = Bitboard(KING_MOVES[i].0 | SQUARES[i].0);
+        masks[Color::White.idx()][i] = Bitboard(around.0 | around.north().0);
+        masks[Color::Black.idx()][i] = Bitboard(around.0 | around.south().0);
+        i += 1;
+    }
+    masks
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::square::Square;
 
     #[test]
     fn test_make_square_masks() {
@@ -81,4 +257,67 @@ mod tests {
         assert!(SQUARES[63] == RANK_8 & FILE_H);
     }
 
+    fn bb(squares: &[Square]) -> Bitboard {
+        squares.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_king_and_pawn_masks() {
+        assert_eq!(KING_MOVES[Square::A1.idx()], bb(&[Square::A2, Square::B1, Square::B2]));
+        assert_eq!(KING_MOVES[Square::E4.idx()].popcount(), 8);
+
+        let white = Color::White.idx();
+        let black = Color::Black.idx();
+        assert_eq!(PAWN_ATTACKS[white][Square::E4.idx()], bb(&[Square::D5, Square::F5]));
+        assert_eq!(PAWN_ATTACKS[black][Square::E4.idx()], bb(&[Square::D3, Square::F3]));
+        assert_eq!(PAWN_ATTACKS[white][Square::A2.idx()], bb(&[Square::B3]));
+        assert_eq!(PAWN_ATTACKS[black][Square::H7.idx()], bb(&[Square::G6]));
+        assert_eq!(PAWN_ATTACKS[white][Square::E8.idx()], Bitboard::EMPTY);
+    }
+
+    #[test]
+    fn test_rays_between_and_line() {
+        assert_eq!(RAYS[Direction::North.idx()][Square::E6.idx()], bb(&[Square::E7, Square::E8]));
+        assert_eq!(RAYS[Direction::SouthWest.idx()][Square::A8.idx()], Bitboard::EMPTY);
+        assert_eq!(RAYS[Direction::NorthEast.idx()][Square::A1.idx()].popcount(), 7);
+
+        assert_eq!(BETWEEN[Square::A1.idx()][Square::D4.idx()], bb(&[Square::B2, Square::C3]));
+        assert_eq!(BETWEEN[Square::D4.idx()][Square::A1.idx()], bb(&[Square::B2, Square::C3]));
+        assert_eq!(BETWEEN[Square::E1.idx()][Square::E8.idx()].popcount(), 6);
+        assert_eq!(BETWEEN[Square::E4.idx()][Square::E5.idx()], Bitboard::EMPTY);
+        assert_eq!(BETWEEN[Square::A1.idx()][Square::B3.idx()], Bitboard::EMPTY);
+
+        assert_eq!(LINE[Square::C1.idx()][Square::H1.idx()], RANK_1);
+        assert_eq!(LINE[Square::D7.idx()][Square::D2.idx()], FILE_D);
+        assert_eq!(LINE[Square::B2.idx()][Square::G7.idx()].popcount(), 8);
+        assert_eq!(LINE[Square::A1.idx()][Square::B3.idx()], Bitboard::EMPTY);
+        for from in Square::all() {
+            for to in Square::all() {
+                let between = BETWEEN[from.idx()][to.idx()];
+                assert_eq!(between & !LINE[from.idx()][to.idx()], Bitboard::EMPTY);
+            }
+        }
+    }
+
+    #[test]
+    fn test_file_and_pawn_structure_masks() {
+        assert_eq!(ADJACENT_FILES[0], FILE_B);
+        assert_eq!(ADJACENT_FILES[4], FILE_D | FILE_F);
+        assert_eq!(ADJACENT_FILES[7], FILE_G);
+
+        let white = Color::White.idx();
+        let black = Color::Black.idx();
+        let white_span = PASSED_PAWN_SPANS[white][Square::E6.idx()];
+        assert_eq!(white_span, bb(&[Square::D7, Square::E7, Square::F7, Square::D8, Square::E8,
+            Square::F8]));
+        let black_span = PASSED_PAWN_SPANS[black][Square::A3.idx()];
+        assert_eq!(black_span, bb(&[Square::A2, Square::B2, Square::A1, Square::B1]));
+
+        assert_eq!(KING_ZONES[white][Square::G1.idx()].popcount(), 9);
+        assert!(KING_ZONES[white][Square::G1.idx()].contains(Square::H3));
+        assert_eq!(KING_ZONES[black][Square::E8.idx()].popcount(), 9);
+        assert!(KING_ZONES[black][Square::E8.idx()].contains(Square::D6));
+        assert_eq!(KING_ZONES[white][Square::E4.idx()].popcount(), 12);
+    }
+
 }
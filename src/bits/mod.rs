@@ -1,2 +1,2 @@
-pub mod biterator;
+pub mod bitboard;
 pub mod masks;
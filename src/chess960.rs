@@ -0,0 +1,141 @@
+//! This file contains functions for generating Chess960 (Fischer Random) start positions.
+//!
+//! Start positions are numbered 0 to 959 with the standard Scharnagl numbering, in which 518 is the
+//! standard chess start position.
+use crate::fen::parse_fen;
+use crate::game_state::{ GameState, PieceType };
+use crate::square::File;
+
+
+/// The number of Chess960 start positions.
+pub const NUM_START_POSITIONS: u16 = 960;
+
+/// The index of the standard chess start position.
+pub const STANDARD_INDEX: u16 = 518;
+
+/// Knight placements among the five back rank squares left after the bishops and queen are placed,
+/// indexed by the knight digit of the position index.
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4),
+];
+
+
+/// Get the back rank piece types of the start position with the given index, from the a-file to
+/// the h-file.
+pub fn back_rank(index: u16) -> Result<[PieceType; 8], String> {
+    if index >= NUM_START_POSITIONS {
+        return Err(format!("Invalid Chess960 start position index: {}", index));
+    }
+    let mut rank = [None; 8];
+    let mut n = index as usize;
+
+    // Light-squared bishop on b, d, f or h, then dark-squared bishop on a, c, e or g.
+    rank[2 * (n % 4) + 1] = Some(PieceType::Bishop);
+    n /= 4;
+    rank[2 * (n % 4)] = Some(PieceType::Bishop);
+    n /= 4;
+
+    place_on_nth_empty(&mut rank, n % 6, PieceType::Queen);
+    n /= 6;
+
+    // Place the second knight first so the first knight's index among empty squares is unchanged.
+    let (first_knight, second_knight) = KNIGHT_PLACEMENTS[n];
+    place_on_nth_empty(&mut rank, second_knight, PieceType::Knight);
+    place_on_nth_empty(&mut rank, first_knight, PieceType::Knight);
+
+    // The king goes between the rooks on the last three squares.
+    for kind in [PieceType::Rook, PieceType::King, PieceType::Rook] {
+        place_on_nth_empty(&mut rank, 0, kind);
+    }
+
+    Ok(rank.map(|kind| kind.unwrap()))
+}
+
+/// Get the FEN string of the start position with the given index. Castle rights are written in
+/// Shredder-FEN.
+pub fn start_fen(index: u16) -> Result<String, String> {
+    let rank = back_rank(index)?;
+    let black_rank: String = rank.iter().map(|&kind| kind_char(kind)).collect();
+    let white_rank = black_rank.to_ascii_uppercase();
+
+    // Kingside rook first, as in "HAha".
+    let black_castle: String = File::all()
+        .rev()
+        .filter(|file| rank[file.idx()] == PieceType::Rook)
+        .map(|file| file.to_string())
+        .collect();
+    let white_castle = black_castle.to_ascii_uppercase();
+
+    Ok(format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+        black_rank, white_rank, white_castle, black_castle,
+    ))
+}
+
+/// Get the GameState of the start position with the given index.
+pub fn start_position(index: u16) -> Result<GameState, String> {
+    Ok(parse_fen(&start_fen(index)?))
+}
+
+
+/// Place the piece type on the nth empty square of the rank.
+fn place_on_nth_empty(rank: &mut [Option<PieceType>; 8], n: usize, kind: PieceType) {
+    let file = (0..8).filter(|&file| rank[file].is_none()).nth(n).expect("Rank is full.");
+    rank[file] = Some(kind);
+}
+
+/// Get the (black) FEN character for the piece type.
+fn kind_char(kind: PieceType) -> char {
+    match kind {
+        PieceType::Pawn => 'p',
+        PieceType::Bishop => 'b',
+        PieceType::Knight => 'n',
+        PieceType::Rook => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King => 'k',
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::CastleRights;
+    use crate::fen::to_fen;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_known_start_positions() {
+        let standard = start_position(STANDARD_INDEX).unwrap();
+        assert_eq!(to_fen(&standard), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(
+            start_fen(0).unwrap(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1",
+        );
+        assert_eq!(
+            start_fen(959).unwrap(),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1",
+        );
+        assert!(start_fen(NUM_START_POSITIONS).is_err());
+    }
+
+    #[test]
+    fn test_all_start_positions_are_valid() {
+        let mut seen = HashSet::new();
+        for index in 0..NUM_START_POSITIONS {
+            let rank = back_rank(index).unwrap();
+            assert!(seen.insert(rank));
+
+            let files_of = |kind| (0..8).filter(|&file| rank[file] == kind).collect::<Vec<_>>();
+            let bishops = files_of(PieceType::Bishop);
+            let rooks = files_of(PieceType::Rook);
+            let king = files_of(PieceType::King)[0];
+            assert_ne!(bishops[0] % 2, bishops[1] % 2);
+            assert!(rooks[0] < king && king < rooks[1]);
+
+            let state = start_position(index).unwrap();
+            assert_eq!(state.validate(), Ok(()));
+            assert_eq!(state.castlerights, CastleRights::ALL);
+        }
+    }
+}
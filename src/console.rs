@@ -0,0 +1,230 @@
+//! This file contains the interactive console used by the bongcloud binary to play through and
+//! inspect positions from the terminal.
+//!
+//! Moves are entered in UCI long algebraic notation or in SAN, and are checked against the board
+//! before they are played. Listing legal moves, perft and search need legal move generation and a
+//! search, which don't exist yet; the commands for them report that instead of doing nothing.
+use crate::chess960;
+use crate::eval::{ self, PawnTable };
+use crate::game_move::GameMove;
+use crate::game_state::{ CastleFiles, Game };
+use crate::move_gen;
+use crate::san;
+
+
+/// The help text printed by the `help` command.
+const HELP: &str = "\
+Commands:
+  <move>       play a move in UCI notation or SAN, e.g. e2e4, e7e8q, Nf3, O-O
+  undo         take back the last move
+  board, d     show the board
+  flip         flip the board
+  fen          show the FEN of the current position
+  fen <FEN>    set up the position from a FEN string
+  eval         show the evaluation, term by term
+  new          start a new game
+  help         show this message
+  quit, exit   leave the console";
+
+
+/// An interactive console holding a Game, which handles one line of input at a time.
+pub struct Console {
+    game: Game,
+    flipped: bool,
+    pawn_table: PawnTable,
+}
+
+impl Console {
+    /// Make a new Console at the standard start position.
+    pub fn new() -> Self {
+        let state = chess960::start_position(chess960::STANDARD_INDEX).unwrap();
+        Console { game: Game::new(state), flipped: false, pawn_table: PawnTable::default() }
+    }
+
+    /// Get the Game being played in this Console.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Draw the current position, from black's point of view if the board is flipped.
+    pub fn board(&self) -> String {
+        self.game.current_state().diagram(self.flipped)
+    }
+
+    /// Handle one line of input, returning the text to show the user.
+    pub fn handle(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+        match command {
+            "" => Ok(String::new()),
+            "help" => Ok(HELP.to_string()),
+            "board" | "d" => Ok(self.board()),
+            "flip" => {
+                self.flipped = !self.flipped;
+                Ok(self.board())
+            },
+            "new" => {
+                *self = Console { flipped: self.flipped, ..Console::new() };
+                Ok(self.board())
+            },
+            "undo" => {
+                self.game.unmake()?;
+                Ok(self.board())
+            },
+            "fen" if args.is_empty() => Ok(self.game.variant().to_fen(&self.game.current_state())),
+            "fen" => self.set_fen(args),
+            "moves" | "perft" => Err(format!("{} needs legal move generation.", command)),
+            "eval" => {
+                let state = self.game.current_state();
+                Ok(eval::breakdown(&state, &mut self.pawn_table).to_string())
+            },
+            "go" => Err("go needs a search.".to_string()),
+            _ => self.play(line),
+        }
+    }
+
+    /// Set up the position from the given FEN string, dropping the moves played so far.
+    fn set_fen(&mut self, fen: &str) -> Result<String, String> {
+        let state = self.game.variant().parse_fen(fen)?;
+        self.game.variant().validate(&state)?;
+        state.validate_rights()?;
+        if move_gen::in_check(&state, !state.side_to_move) {
+            return Err(format!("The side not to move is in check: {}", fen));
+        }
+        self.game = Game::new(state);
+        Ok(self.board())
+    }
+
+    /// Play the given move, in UCI notation or SAN, unless it can't be made on the board or leaves
+    /// the mover's king in check.
+    fn play(&mut self, move_str: &str) -> Result<String, String> {
+        let state = self.game.current_state();
+        let chess960 = state.castle_files() != CastleFiles::STANDARD;
+        let game_move = match GameMove::from_uci(move_str, &state, chess960) {
+            Ok(game_move) => game_move,
+            Err(_) => san::parse_san(move_str, &state)?,
+        };
+        let is_drop = game_move.drop_kind().is_some();
+        if (is_drop && !self.game.variant().has_drops())
+            || !move_gen::is_pseudo_legal(&state, game_move)
+        {
+            return Err(format!("Illegal move: {}", move_str));
+        }
+
+        self.game.make(game_move);
+        if move_gen::in_check(&self.game.current_state(), state.side_to_move) {
+            self.game.unmake()?;
+            return Err(format!("Illegal move: {}", move_str));
+        }
+        Ok(self.board())
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_play_and_undo() {
+        let mut console = Console::new();
+        assert_eq!(console.handle("fen"), Ok(START_FEN.to_string()));
+        console.handle("e2e4").unwrap();
+        console.handle("e7e5").unwrap();
+        assert_eq!(
+            console.handle("fen").unwrap(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+        );
+        assert!(console.handle("e2e4").is_err());
+        assert!(console.handle("frobnicate").is_err());
+
+        console.handle("undo").unwrap();
+        console.handle("undo").unwrap();
+        assert_eq!(console.handle("fen"), Ok(START_FEN.to_string()));
+        assert!(console.handle("undo").is_err());
+    }
+
+    #[test]
+    fn test_rejects_moves_into_check() {
+        let mut console = Console::new();
+        console.handle("fen 4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        assert!(console.handle("e1d1").is_ok());
+        console.handle("undo").unwrap();
+        assert!(console.handle("e1d2").is_err());
+        assert_eq!(console.game().depth_from_start(), 0);
+        assert!(console.handle("fen 8/8/8/8/8/8/8/8 w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn test_rejects_impossible_moves() {
+        let mut console = Console::new();
+        for bad_move in ["e1g1", "e1c1", "a1h8", "d1d3", "b1b3", "e2e5", "e2d3", "P@e4", "0000"] {
+            assert!(console.handle(bad_move).is_err(), "{}", bad_move);
+        }
+        assert_eq!(console.handle("fen"), Ok(START_FEN.to_string()));
+
+        console.handle("fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        console.handle("e1g1").unwrap();
+        console.handle("O-O-O").unwrap();
+        assert_eq!(console.handle("fen").unwrap(), "2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2");
+    }
+
+    #[test]
+    fn test_san_moves() {
+        let mut console = Console::new();
+        for san_move in ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "O-O"] {
+            console.handle(san_move).unwrap();
+        }
+        assert_eq!(
+            console.handle("fen").unwrap(),
+            "r1bqkbnr/1pp2ppp/p1p5/4p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 1 5",
+        );
+        assert!(console.handle("Qxd1+").is_err());
+        assert!(console.handle("Kf8").is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_fens() {
+        let mut console = Console::new();
+        for bad_fen in [
+            "x w KQkq - 1 0",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1",
+            "4k3/8/8/8/8/8/4R3/4K3 w - - 0 1",
+        ] {
+            assert!(console.handle(&format!("fen {}", bad_fen)).is_err(), "{}", bad_fen);
+        }
+        assert_eq!(console.handle("fen"), Ok(START_FEN.to_string()));
+    }
+
+    #[test]
+    fn test_eval() {
+        let mut console = Console::new();
+        let output = console.handle("eval").unwrap();
+        assert!(output.contains("King attacks"));
+        assert!(output.ends_with("eval 0 for white"));
+    }
+
+    #[test]
+    fn test_board_diagram() {
+        let mut console = Console::new();
+        let board = console.handle("d").unwrap();
+        assert!(board.starts_with("8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜\n"));
+        assert!(board.ends_with("1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖\n  a b c d e f g h\n"));
+
+        let flipped = console.handle("flip").unwrap();
+        assert!(flipped.starts_with("1 ♖ ♘ ♗ ♔ ♕ ♗ ♘ ♖\n"));
+        assert!(flipped.ends_with("  h g f e d c b a\n"));
+    }
+}
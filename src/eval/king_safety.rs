@@ -0,0 +1,148 @@
+//! This file contains the king safety evaluation terms: attacks on the squares around the king,
+//! and the friendly and enemy pawns in front of it.
+use crate::bits::bitboard::Bitboard;
+use crate::bits::masks;
+use crate::eval::{ self, Score };
+use crate::game_state::{ Color, GameState, PieceType };
+use crate::square::Square;
+
+
+/// How much one attacked king zone square counts towards the danger to the king, indexed by the
+/// attacking PieceType.
+const ATTACK_WEIGHTS: [i32; 6] = [0, 2, 2, 3, 5, 0];
+
+/// The middlegame penalty for each amount of danger to the king; the endgame penalty is an eighth
+/// of it. Grows quadratically, so that several attackers together count for more than each alone.
+const SAFETY_TABLE: [i32; 64] = make_safety_table();
+
+/// Bonuses for the nearest friendly pawn on each file in front of the king, indexed by how many
+/// ranks in front of the king it is. Index 3 is for a pawn three or more ranks away, or none.
+const SHIELD: [Score; 4] = [
+    Score::new(0, 0),
+    Score::new(12, 0),
+    Score::new(6, 0),
+    Score::new(-12, 0),
+];
+
+/// Penalties for the nearest enemy pawn on each file in front of the king, indexed by how many
+/// ranks in front of the king it is. Index 4 is for a pawn four or more ranks away, or none.
+const STORM: [Score; 5] = [
+    Score::new(0, 0),
+    Score::new(-5, 0),
+    Score::new(-20, 0),
+    Score::new(-10, 0),
+    Score::new(0, 0),
+];
+
+
+/// Score the danger to the given side's king from enemy pieces attacking its king zone. Only
+/// counts once at least two pieces attack the zone.
+pub fn king_attacks(state: &GameState, color: Color) -> Score {
+    let Some(king_sq) = state.pieces(color, PieceType::King).lsb() else { return Score::ZERO };
+    let zone = masks::KING_ZONES[color.idx()][king_sq.idx()];
+    let occupancy = state.all_pieces();
+
+    let mut attackers = 0;
+    let mut danger = 0;
+    for kind in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+        for sq in state.pieces(!color, kind) {
+            let hits = eval::piece_attacks(kind, !color, sq, occupancy) & zone;
+            if hits.is_empty() { continue }
+            attackers += 1;
+            danger += ATTACK_WEIGHTS[kind as usize] * hits.popcount() as i32;
+        }
+    }
+
+    if attackers < 2 { return Score::ZERO }
+    let penalty = SAFETY_TABLE[danger.min(63) as usize];
+    Score::new(-penalty, -penalty / 8)
+}
+
+/// Score the given side's pawns on the king's file and the files beside it. Only counts while
+/// the king is on its first two ranks.
+pub fn pawn_shield(state: &GameState, color: Color) -> Score {
+    king_file_pawns(state, color, color, |dist| SHIELD[dist.min(3)])
+}
+
+/// Score the enemy pawns advancing on the given side's king's file and the files beside it. Only
+/// counts while the king is on its first two ranks.
+pub fn pawn_storm(state: &GameState, color: Color) -> Score {
+    king_file_pawns(state, color, !color, |dist| STORM[dist.min(4)])
+}
+
+/// Sum file_score over the king's file and the files beside it, given how many ranks in front of
+/// the given side's king the nearest pawn of pawn_color on the file is, or usize::MAX if there is
+/// none. Returns zero if the side has no king or its king is past its second rank.
+fn king_file_pawns<F: Fn(usize) -> Score>(
+    state: &GameState,
+    color: Color,
+    pawn_color: Color,
+    file_score: F,
+) -> Score {
+    let Some(king_sq) = state.pieces(color, PieceType::King).lsb() else { return Score::ZERO };
+    if king_sq.rank().relative(color).idx() > 1 { return Score::ZERO }
+
+    let in_front = masks::PASSED_PAWN_SPANS[color.idx()][king_sq.idx()]
+        & state.pieces(pawn_color, PieceType::Pawn);
+    (-1..=1)
+        .filter_map(|delta| king_sq.file().offset(delta))
+        .fold(Score::ZERO, |score, file| {
+            let pawns = in_front & masks::FILES[file.idx()];
+            let dist = nearest(pawns, color)
+                .map_or(usize::MAX, |sq| sq.rank().distance(king_sq.rank()) as usize);
+            score + file_score(dist)
+        })
+}
+
+/// Get the square of the given pawns nearest to the given side's first rank.
+fn nearest(pawns: Bitboard, color: Color) -> Option<Square> {
+    match color {
+        Color::White => pawns.lsb(),
+        Color::Black => pawns.msb(),
+    }
+}
+
+/// Make the king safety table.
+const fn make_safety_table() -> [i32; 64] {
+    let mut table = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        let penalty = (i * i / 4) as i32;
+        table[i] = if penalty < 500 { penalty } else { 500 };
+        i += 1;
+    }
+    table
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    #[test]
+    fn test_king_attacks() {
+        // The queen alone is not enough. With the knight beside it, the queen hits f7, g6, h6 and
+        // h7 (4 * 5) and the knight hits f7 and h7 (2 * 2).
+        let queen = parse_fen("6k1/5ppp/8/7Q/8/8/8/6K1 b - - 0 1");
+        assert_eq!(king_attacks(&queen, Color::Black), Score::ZERO);
+        let both = parse_fen("6k1/5ppp/8/6NQ/8/8/8/6K1 b - - 0 1");
+        let penalty = SAFETY_TABLE[24];
+        assert_eq!(king_attacks(&both, Color::Black), Score::new(-penalty, -penalty / 8));
+        assert_eq!(king_attacks(&both, Color::White), Score::ZERO);
+    }
+
+    #[test]
+    fn test_pawn_shield_and_storm() {
+        let castled = parse_fen("6k1/8/8/8/8/7p/5PP1/6K1 w - - 0 1");
+        assert_eq!(pawn_shield(&castled, Color::White), SHIELD[1] * 2 + SHIELD[3]);
+        assert_eq!(pawn_storm(&castled, Color::White), STORM[2]);
+
+        // The king has left its first two ranks.
+        let advanced = parse_fen("6k1/8/8/8/8/6K1/5PP1/8 w - - 0 1");
+        assert_eq!(pawn_shield(&advanced, Color::White), Score::ZERO);
+
+        let black = parse_fen("6k1/5p1p/6p1/8/8/8/8/6K1 b - - 0 1");
+        assert_eq!(pawn_shield(&black, Color::Black), SHIELD[1] * 2 + SHIELD[2]);
+    }
+}
@@ -0,0 +1,368 @@
+//! This file contains the static evaluation of a GameState.
+//!
+//! Terms are scored separately for the middlegame and the endgame as a Score, and the two are
+//! blended by the game phase, which falls from MAX_PHASE towards 0 as pieces come off the board.
+//! Scores are in centipawns from white's point of view until the final result, which is from the
+//! side to move's point of view.
+//!
+//! Each term is scored for each side separately, so that `breakdown` can show where an evaluation
+//! comes from.
+use std::fmt;
+use std::ops::{ Add, AddAssign, Div, Mul, Neg, Sub, SubAssign };
+use crate::bits::bitboard::Bitboard;
+use crate::bits::masks;
+use crate::game_state::{ Color, GameState, PieceType };
+use crate::move_gen;
+use crate::square::Square;
+
+pub mod king_safety;
+pub mod pawns;
+pub mod pieces;
+
+pub use pawns::PawnTable;
+
+
+/// The phase of a position with all pieces on the board.
+pub const MAX_PHASE: i32 = 24;
+
+/// How much each piece type counts towards the game phase, indexed by PieceType.
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+
+/// The value of each piece type, indexed by PieceType.
+const MATERIAL: [Score; 6] = [
+    Score::new(100, 120),
+    Score::new(330, 320),
+    Score::new(320, 300),
+    Score::new(500, 550),
+    Score::new(950, 1000),
+    Score::new(0, 0),
+];
+
+
+/// A term of the evaluation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Term {
+    Material = 0,
+    PawnStructure = 1,
+    PassedPawns = 2,
+    Mobility = 3,
+    KingAttacks = 4,
+    PawnShield = 5,
+    PawnStorm = 6,
+    BishopPair = 7,
+    RookFiles = 8,
+    Outposts = 9,
+    Threats = 10,
+}
+
+impl Term {
+    pub const ALL: [Term; 11] = [
+        Term::Material,
+        Term::PawnStructure,
+        Term::PassedPawns,
+        Term::Mobility,
+        Term::KingAttacks,
+        Term::PawnShield,
+        Term::PawnStorm,
+        Term::BishopPair,
+        Term::RookFiles,
+        Term::Outposts,
+        Term::Threats,
+    ];
+
+    /// Get the name of this term, as shown in an EvalBreakdown.
+    pub fn name(self) -> &'static str {
+        match self {
+            Term::Material => "Material",
+            Term::PawnStructure => "Pawn structure",
+            Term::PassedPawns => "Passed pawns",
+            Term::Mobility => "Mobility",
+            Term::KingAttacks => "King attacks",
+            Term::PawnShield => "Pawn shield",
+            Term::PawnStorm => "Pawn storm",
+            Term::BishopPair => "Bishop pair",
+            Term::RookFiles => "Rook files",
+            Term::Outposts => "Outposts",
+            Term::Threats => "Threats",
+        }
+    }
+}
+
+
+/// A middlegame and endgame score pair.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const ZERO: Score = Score::new(0, 0);
+
+    /// Make a Score from its middlegame and endgame parts.
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Score { mg, eg }
+    }
+
+    /// Blend the middlegame and endgame parts for the given game phase.
+    pub fn taper(self, phase: i32) -> i32 {
+        let phase = phase.clamp(0, MAX_PHASE);
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Score {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Self;
+
+    fn mul(self, factor: i32) -> Self {
+        Score::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+impl Div<i32> for Score {
+    type Output = Self;
+
+    fn div(self, divisor: i32) -> Self {
+        Score::new(self.mg / divisor, self.eg / divisor)
+    }
+}
+
+
+/// The score of each term of the evaluation for each side, along with the game phase used to
+/// blend them.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EvalBreakdown {
+    /// Indexed by [Term][Color]. Each side's score is from its own point of view.
+    pub scores: [[Score; 2]; 11],
+    pub phase: i32,
+}
+
+impl EvalBreakdown {
+    /// Get the score of the given term for the given side, from that side's point of view.
+    pub fn get(&self, term: Term, color: Color) -> Score {
+        self.scores[term as usize][color.idx()]
+    }
+
+    /// Get the score of the given term from white's point of view.
+    pub fn net(&self, term: Term) -> Score {
+        self.get(term, Color::White) - self.get(term, Color::Black)
+    }
+
+    /// Get the total score from white's point of view.
+    pub fn total(&self) -> Score {
+        Term::ALL.into_iter().fold(Score::ZERO, |total, term| total + self.net(term))
+    }
+
+    /// Get the final evaluation from white's point of view.
+    pub fn eval(&self) -> i32 {
+        self.total().taper(self.phase)
+    }
+}
+
+impl fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<15}|     White     |     Black     |     Total", "Term")?;
+        writeln!(f, "{:<15}|   MG     EG   |   MG     EG   |   MG     EG", "")?;
+        for term in Term::ALL {
+            let (white, black, net) =
+                (self.get(term, Color::White), self.get(term, Color::Black), self.net(term));
+            writeln!(
+                f,
+                "{:<15}| {:>5}  {:>5}  | {:>5}  {:>5}  | {:>5}  {:>5}",
+                term.name(), white.mg, white.eg, black.mg, black.eg, net.mg, net.eg,
+            )?;
+        }
+        let total = self.total();
+        writeln!(f, "{:<15}|{:15}|{:15}| {:>5}  {:>5}", "Total", "", "", total.mg, total.eg)?;
+        write!(f, "Phase {}/{}, eval {} for white", self.phase, MAX_PHASE, self.eval())
+    }
+}
+
+
+/// The squares attacked by each side, by piece type.
+pub struct Attacks {
+    /// Indexed by [Color][PieceType].
+    pub by_kind: [[Bitboard; 6]; 2],
+    /// Indexed by Color.
+    pub all: [Bitboard; 2],
+}
+
+impl Attacks {
+    /// Find the squares attacked by each side in the given GameState.
+    pub fn new(state: &GameState) -> Self {
+        let mut attacks = Attacks {
+            by_kind: [[Bitboard::EMPTY; 6]; 2],
+            all: [Bitboard::EMPTY; 2],
+        };
+        let occupancy = state.all_pieces();
+        for color in Color::all() {
+            for kind in PieceType::all() {
+                for sq in state.pieces(color, kind) {
+                    let piece_attacks = piece_attacks(kind, color, sq, occupancy);
+                    attacks.by_kind[color.idx()][kind as usize] |= piece_attacks;
+                    attacks.all[color.idx()] |= piece_attacks;
+                }
+            }
+        }
+        attacks
+    }
+
+    /// Get the squares attacked by the given side's pieces of the given type.
+    pub fn get(&self, color: Color, kind: PieceType) -> Bitboard {
+        self.by_kind[color.idx()][kind as usize]
+    }
+}
+
+
+/// Evaluate the given GameState from the side to move's point of view, using the PawnTable to
+/// cache pawn structure.
+pub fn evaluate(state: &GameState, pawn_table: &mut PawnTable) -> i32 {
+    let white_eval = breakdown(state, pawn_table).eval();
+    match state.side_to_move {
+        Color::White => white_eval,
+        Color::Black => -white_eval,
+    }
+}
+
+/// Score each term of the evaluation for each side.
+pub fn breakdown(state: &GameState, pawn_table: &mut PawnTable) -> EvalBreakdown {
+    let pawn_entry = pawn_table.probe(state);
+    let attacks = Attacks::new(state);
+    let mut result = EvalBreakdown { scores: [[Score::ZERO; 2]; 11], phase: phase(state) };
+    for color in Color::all() {
+        let passed = pawn_entry.passed[color.idx()];
+        let side_scores = [
+            (Term::Material, material(state, color)),
+            (Term::PawnStructure, pawn_entry.structure[color.idx()]),
+            (Term::PassedPawns, pawns::passed_pawns(state, color, passed)),
+            (Term::Mobility, pieces::mobility(state, color, &attacks)),
+            (Term::KingAttacks, king_safety::king_attacks(state, color)),
+            (Term::PawnShield, king_safety::pawn_shield(state, color)),
+            (Term::PawnStorm, king_safety::pawn_storm(state, color)),
+            (Term::BishopPair, pieces::bishop_pair(state, color)),
+            (Term::RookFiles, pieces::rook_files(state, color)),
+            (Term::Outposts, pieces::outposts(state, color)),
+            (Term::Threats, pieces::threats(state, color, &attacks)),
+        ];
+        for (term, score) in side_scores {
+            result.scores[term as usize][color.idx()] = score;
+        }
+    }
+    result
+}
+
+/// Get the game phase of the given GameState, from MAX_PHASE with all pieces on the board down to
+/// 0 with only kings and pawns. Promotions can push the count past MAX_PHASE, so it is capped.
+pub fn phase(state: &GameState) -> i32 {
+    let phase: i32 = PieceType::all()
+        .map(|kind| PHASE_WEIGHTS[kind as usize] * state.pieces_of_kind(kind).popcount() as i32)
+        .sum();
+    phase.min(MAX_PHASE)
+}
+
+/// Get the squares attacked by a piece of the given type and color on the given square, given the
+/// occupancy of the board.
+pub fn piece_attacks(kind: PieceType, color: Color, sq: Square, occupancy: Bitboard) -> Bitboard {
+    match kind {
+        PieceType::Pawn => masks::PAWN_ATTACKS[color.idx()][sq.idx()],
+        PieceType::Knight => masks::KNIGHT_MOVES[sq.idx()],
+        PieceType::Bishop => move_gen::bishop_attacks(sq, occupancy),
+        PieceType::Rook => move_gen::rook_attacks(sq, occupancy),
+        PieceType::Queen => move_gen::queen_attacks(sq, occupancy),
+        PieceType::King => masks::KING_MOVES[sq.idx()],
+    }
+}
+
+/// Get the material score of the given side.
+///
+/// TODO: add piece-square tables, as their own term, so that piece placement is scored beyond
+/// what mobility and the king safety terms pick up.
+pub fn material(state: &GameState, color: Color) -> Score {
+    PieceType::all().fold(Score::ZERO, |score, kind| {
+        score + MATERIAL[kind as usize] * state.pieces(color, kind).popcount() as i32
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    #[test]
+    fn test_taper_and_phase() {
+        let score = Score::new(100, 20);
+        assert_eq!(score.taper(MAX_PHASE), 100);
+        assert_eq!(score.taper(0), 20);
+        assert_eq!(score.taper(MAX_PHASE / 2), 60);
+
+        let start = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(phase(&start), MAX_PHASE);
+        assert_eq!(phase(&parse_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1")), 0);
+        assert_eq!(phase(&parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")), 2);
+    }
+
+    #[test]
+    fn test_evaluate_is_symmetric() {
+        let mut pawn_table = PawnTable::default();
+        let start = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(evaluate(&start, &mut pawn_table), 0);
+
+        let white = parse_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1");
+        let black = parse_fen("r3k3/4p3/8/8/8/8/8/4K3 b - - 0 1");
+        let eval = evaluate(&white, &mut pawn_table);
+        assert!(eval > 400);
+        assert_eq!(evaluate(&black, &mut pawn_table), eval);
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let mut pawn_table = PawnTable::default();
+        let state = parse_fen("r3k3/8/8/8/8/8/8/1B2KB2 w - - 0 1");
+        let breakdown = breakdown(&state, &mut pawn_table);
+        assert_eq!(breakdown.get(Term::Material, Color::White), MATERIAL[1] * 2);
+        assert_eq!(breakdown.get(Term::Material, Color::Black), MATERIAL[3]);
+        assert_ne!(breakdown.get(Term::BishopPair, Color::White), Score::ZERO);
+        assert_eq!(breakdown.get(Term::BishopPair, Color::Black), Score::ZERO);
+        assert_eq!(breakdown.eval(), evaluate(&state, &mut pawn_table));
+
+        let table = breakdown.to_string();
+        assert!(Term::ALL.iter().all(|term| table.contains(term.name())));
+    }
+}
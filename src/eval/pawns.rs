@@ -0,0 +1,285 @@
+//! This file contains the pawn structure evaluation and the pawn hash table which caches it.
+//!
+//! Pawn structure only depends on where the pawns are, so it is cached under the pawn hash kept by
+//! GameState. Passed pawns are cached as a Bitboard rather than scored, since how much a passer
+//! is worth also depends on the pieces blocking it.
+use crate::bits::bitboard::{ Bitboard, Direction };
+use crate::bits::masks;
+use crate::eval::Score;
+use crate::game_state::{ Color, GameState, PieceType };
+use crate::square::Square;
+
+
+/// Default number of entries in a PawnTable.
+const DEFAULT_ENTRIES: usize = 1 << 14;
+
+/// Penalty for a pawn with a friendly pawn in front of it on the same file.
+const DOUBLED: Score = Score::new(-10, -25);
+/// Penalty for a pawn with no friendly pawns on the adjacent files.
+const ISOLATED: Score = Score::new(-8, -15);
+/// Penalty for a pawn which no friendly pawn can defend and which can't safely advance.
+const BACKWARD: Score = Score::new(-8, -12);
+
+// Bonuses indexed by the pawn's rank from its own side's point of view.
+
+/// For a pawn with no enemy pawns in front of it on its own or the adjacent files.
+const PASSED: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(5, 10),
+    Score::new(10, 15),
+    Score::new(15, 30),
+    Score::new(30, 55),
+    Score::new(55, 95),
+    Score::new(90, 150),
+    Score::new(0, 0),
+];
+/// For a pawn on a file with no enemy pawns in front of it, whose way is guarded by no more enemy
+/// pawns on the adjacent files than it has friendly pawns to trade them off.
+const CANDIDATE: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(2, 5),
+    Score::new(4, 8),
+    Score::new(8, 15),
+    Score::new(15, 30),
+    Score::new(25, 50),
+    Score::new(0, 0),
+    Score::new(0, 0),
+];
+/// For a pawn defended by a friendly pawn.
+const CONNECTED: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(8, 6),
+    Score::new(10, 8),
+    Score::new(18, 14),
+    Score::new(30, 25),
+    Score::new(50, 45),
+    Score::new(0, 0),
+];
+/// For a pawn with a friendly pawn beside it.
+const PHALANX: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(4, 2),
+    Score::new(6, 4),
+    Score::new(10, 8),
+    Score::new(18, 15),
+    Score::new(35, 30),
+    Score::new(60, 60),
+    Score::new(0, 0),
+];
+
+/// The cached pawn structure of a position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PawnEntry {
+    pub key: u64,
+    /// The pawn structure score of each side, not counting passed pawns.
+    pub structure: [Score; 2],
+    /// The passed pawns of each side.
+    pub passed: [Bitboard; 2],
+}
+
+impl PawnEntry {
+    /// Evaluate the pawn structure of the given GameState, which has the given pawn key.
+    pub fn new(state: &GameState, key: u64) -> Self {
+        let mut entry = PawnEntry {
+            key,
+            structure: [Score::ZERO; 2],
+            passed: [Bitboard::EMPTY; 2],
+        };
+        for color in Color::all() {
+            let (structure, passed) = pawn_structure(state, color);
+            entry.structure[color.idx()] = structure;
+            entry.passed[color.idx()] = passed;
+        }
+        entry
+    }
+}
+
+
+/// A hash table of PawnEntries, indexed by pawn key. Colliding entries replace each other.
+pub struct PawnTable {
+    entries: Vec<Option<PawnEntry>>,
+}
+
+impl PawnTable {
+    /// Make an empty PawnTable with room for the given number of entries, rounded up to a power
+    /// of two.
+    pub fn new(num_entries: usize) -> Self {
+        PawnTable { entries: vec![None; num_entries.max(1).next_power_of_two()] }
+    }
+
+    /// Get the pawn structure of the given GameState, evaluating and storing it if it isn't
+    /// already in the table.
+    pub fn probe(&mut self, state: &GameState) -> PawnEntry {
+        let key = state.pawn_hash();
+        let idx = key as usize & (self.entries.len() - 1);
+        match self.entries[idx] {
+            Some(entry) if entry.key == key => entry,
+            _ => {
+                let entry = PawnEntry::new(state, key);
+                self.entries[idx] = Some(entry);
+                entry
+            },
+        }
+    }
+
+    /// Remove all entries from the table.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTRIES)
+    }
+}
+
+
+/// Score the pawn structure of the given side, not counting passed pawns, and find its passed
+/// pawns.
+fn pawn_structure(state: &GameState, color: Color) -> (Score, Bitboard) {
+    let own_pawns = state.pieces(color, PieceType::Pawn);
+    let enemy_pawns = state.pieces(!color, PieceType::Pawn);
+    let enemy_attacks = pawn_attacks(enemy_pawns, !color);
+
+    let mut score = Score::ZERO;
+    let mut passed = Bitboard::EMPTY;
+    for sq in own_pawns {
+        let rank = sq.rank().relative(color).idx();
+        let file = sq.file().idx();
+        let front = masks::RAYS[forward(color).idx()][sq.idx()];
+        let span = masks::PASSED_PAWN_SPANS[color.idx()][sq.idx()];
+        let adjacent = masks::ADJACENT_FILES[file];
+        // Friendly pawns on the adjacent files which are level with or behind this pawn.
+        let helpers = own_pawns & adjacent & !span;
+        let sentries = enemy_pawns & span & !front;
+
+        let doubled = !(own_pawns & front).is_empty();
+        let isolated = (own_pawns & adjacent).is_empty();
+        let supported = !(masks::PAWN_ATTACKS[(!color).idx()][sq.idx()] & own_pawns).is_empty();
+        let phalanx = !(masks::RANKS[sq.rank().idx()] & adjacent & own_pawns).is_empty();
+        let stop = sq.forward(color);
+
+        if doubled { score += DOUBLED }
+        if isolated {
+            score += ISOLATED;
+        } else if helpers.is_empty() && stop.is_some_and(|stop| enemy_attacks.contains(stop)) {
+            score += BACKWARD;
+        }
+        if supported { score += CONNECTED[rank] }
+        if phalanx { score += PHALANX[rank] }
+
+        if !doubled && (enemy_pawns & span).is_empty() {
+            passed |= Bitboard::from_square(sq);
+        } else if !doubled
+            && (enemy_pawns & front).is_empty()
+            && helpers.popcount() >= sentries.popcount()
+        {
+            score += CANDIDATE[rank];
+        }
+    }
+    (score, passed)
+}
+
+/// Score the given passed pawns of the given side. A passer's bonus grows with its rank and
+/// shrinks when pieces stand in its way: it is halved if its stop square is occupied, and cut by a
+/// quarter if a piece is further up its path.
+pub fn passed_pawns(state: &GameState, color: Color, passed: Bitboard) -> Score {
+    passed.into_iter().fold(Score::ZERO, |score, sq| {
+        let bonus = PASSED[sq.rank().relative(color).idx()];
+        let path = masks::RAYS[forward(color).idx()][sq.idx()] & state.all_pieces();
+        let stop_blocked = sq.forward(color).is_some_and(|stop| path.contains(stop));
+        score + if stop_blocked {
+            bonus / 2
+        } else if !path.is_empty() {
+            bonus * 3 / 4
+        } else {
+            bonus
+        }
+    })
+}
+
+/// Get the squares attacked by the given pawns of the given color.
+fn pawn_attacks(pawns: Bitboard, color: Color) -> Bitboard {
+    pawns.into_iter().fold(Bitboard::EMPTY, |attacks, sq: Square| {
+        attacks | masks::PAWN_ATTACKS[color.idx()][sq.idx()]
+    })
+}
+
+/// Get the direction the given side's pawns move in.
+fn forward(color: Color) -> Direction {
+    match color {
+        Color::White => Direction::North,
+        Color::Black => Direction::South,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    fn structure(fen: &str, color: Color) -> (Score, Bitboard) {
+        pawn_structure(&parse_fen(fen), color)
+    }
+
+    #[test]
+    fn test_pawn_hash() {
+        let a = parse_fen("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1");
+        let b = parse_fen("4k3/pp6/8/8/8/8/PP6/R3K3 b - - 0 1");
+        let c = parse_fen("4k3/pp6/8/8/8/1P6/P7/4K3 w - - 0 1");
+        assert_eq!(a.pawn_hash(), b.pawn_hash());
+        assert_ne!(a.pawn_hash(), c.pawn_hash());
+        assert_eq!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").pawn_hash(), 0);
+    }
+
+    #[test]
+    fn test_pawn_terms() {
+        // Doubled and isolated: both h-pawns are isolated, the rear one is also doubled.
+        let (score, _) = structure("4k3/8/8/8/8/7P/7P/4K3 w - - 0 1", Color::White);
+        assert_eq!(score, ISOLATED * 2 + DOUBLED);
+
+        // Connected and phalanx: d4/e4 side by side, c3 defends d4.
+        let (score, _) = structure("4k3/8/8/8/3PP3/2P5/8/4K3 w - - 0 1", Color::White);
+        assert_eq!(score, PHALANX[3] * 2 + CONNECTED[3]);
+
+        // Backward: d2 has no pawn level with or behind it on the adjacent files, and its stop
+        // square is attacked by the c4 pawn.
+        let (score, passed) = structure("4k3/8/8/8/2p5/4P3/3P4/4K3 w - - 0 1", Color::White);
+        assert_eq!(score, BACKWARD + CONNECTED[2]);
+        assert_eq!(passed, Bitboard::from_square(Square::E3));
+    }
+
+    #[test]
+    fn test_passed_and_candidate_pawns() {
+        // a5 is passed. c4 is a candidate: the d6 sentry is matched by the d3 helper. g3 is on a
+        // file with an enemy pawn in front of it.
+        let fen = "4k3/6p1/3p4/P7/2P5/3P2P1/8/4K3 w - - 0 1";
+        let (score, passed) = structure(fen, Color::White);
+        assert_eq!(passed, Bitboard::from_square(Square::A5));
+        assert_eq!(score, ISOLATED * 2 + CONNECTED[3] + CANDIDATE[3]);
+
+        let state = parse_fen(fen);
+        assert_eq!(passed_pawns(&state, Color::White, passed), PASSED[4]);
+        let blocked = parse_fen("4k3/n5p1/3p4/P7/2P5/3P2P1/8/4K3 w - - 0 1");
+        assert_eq!(passed_pawns(&blocked, Color::White, passed), PASSED[4] * 3 / 4);
+        let stopped = parse_fen("4k3/6p1/n2p4/P7/2P5/3P2P1/8/4K3 w - - 0 1");
+        assert_eq!(passed_pawns(&stopped, Color::White, passed), PASSED[4] / 2);
+    }
+
+    #[test]
+    fn test_pawn_table() {
+        let state = parse_fen("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1");
+        let mut table = PawnTable::new(1);
+        let entry = table.probe(&state);
+        assert_eq!(entry, PawnEntry::new(&state, state.pawn_hash()));
+        assert_eq!(table.probe(&state), entry);
+
+        let other = parse_fen("4k3/8/8/8/8/8/7P/4K3 w - - 0 1");
+        assert_ne!(table.probe(&other).structure, entry.structure);
+        table.clear();
+        assert_eq!(table.probe(&state), entry);
+    }
+}
@@ -0,0 +1,166 @@
+//! This file contains the evaluation terms for piece activity: mobility, the bishop pair, rooks on
+//! open files, outposts and threats.
+use crate::bits::masks;
+use crate::eval::{ self, Attacks, Score };
+use crate::game_state::{ Color, GameState, PieceType };
+
+
+/// Mobility bonus per safe square a piece can move to, indexed by PieceType, along with the number
+/// of squares at which the bonus is zero.
+const MOBILITY: [(Score, i32); 6] = [
+    (Score::new(0, 0), 0),
+    (Score::new(5, 5), 7),
+    (Score::new(4, 4), 4),
+    (Score::new(2, 4), 7),
+    (Score::new(1, 2), 14),
+    (Score::new(0, 0), 0),
+];
+
+const BISHOP_PAIR: Score = Score::new(30, 50);
+const ROOK_OPEN_FILE: Score = Score::new(25, 10);
+const ROOK_SEMI_OPEN_FILE: Score = Score::new(12, 6);
+
+/// For a knight or bishop on the 4th to 6th rank which is defended by a pawn and can't be driven
+/// away by an enemy pawn.
+const KNIGHT_OUTPOST: Score = Score::new(25, 15);
+const BISHOP_OUTPOST: Score = Score::new(12, 8);
+
+/// For each enemy piece other than a pawn or king attacked by a pawn.
+const THREAT_BY_PAWN: Score = Score::new(40, 30);
+/// For each enemy rook or queen attacked by a knight or bishop.
+const THREAT_BY_MINOR: Score = Score::new(25, 20);
+/// For each enemy queen attacked by a rook.
+const THREAT_BY_ROOK: Score = Score::new(25, 20);
+/// For each enemy piece other than a pawn or king which is attacked and not defended.
+const HANGING: Score = Score::new(20, 15);
+
+
+/// Score the mobility of the given side's knights, bishops, rooks and queens: the squares each
+/// attacks which aren't occupied by its own pieces or attacked by enemy pawns.
+pub fn mobility(state: &GameState, color: Color, attacks: &Attacks) -> Score {
+    let safe = !state.side_pieces(color) & !attacks.get(!color, PieceType::Pawn);
+    let occupancy = state.all_pieces();
+    [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen]
+        .into_iter()
+        .fold(Score::ZERO, |score, kind| {
+            let (bonus, baseline) = MOBILITY[kind as usize];
+            state.pieces(color, kind).into_iter().fold(score, |score, sq| {
+                let squares = eval::piece_attacks(kind, color, sq, occupancy) & safe;
+                score + bonus * (squares.popcount() as i32 - baseline)
+            })
+        })
+}
+
+/// Score the bishop pair of the given side.
+pub fn bishop_pair(state: &GameState, color: Color) -> Score {
+    if state.pieces(color, PieceType::Bishop).popcount() >= 2 { BISHOP_PAIR } else { Score::ZERO }
+}
+
+/// Score the given side's rooks on files with no pawns (open) or no friendly pawns (semi-open).
+pub fn rook_files(state: &GameState, color: Color) -> Score {
+    let own_pawns = state.pieces(color, PieceType::Pawn);
+    let all_pawns = state.pieces_of_kind(PieceType::Pawn);
+    state.pieces(color, PieceType::Rook).into_iter().fold(Score::ZERO, |score, sq| {
+        let file = masks::FILES[sq.file().idx()];
+        if (file & all_pawns).is_empty() {
+            score + ROOK_OPEN_FILE
+        } else if (file & own_pawns).is_empty() {
+            score + ROOK_SEMI_OPEN_FILE
+        } else {
+            score
+        }
+    })
+}
+
+/// Score the given side's knights and bishops on outposts.
+pub fn outposts(state: &GameState, color: Color) -> Score {
+    let own_pawns = state.pieces(color, PieceType::Pawn);
+    let enemy_pawns = state.pieces(!color, PieceType::Pawn);
+    [(PieceType::Knight, KNIGHT_OUTPOST), (PieceType::Bishop, BISHOP_OUTPOST)]
+        .into_iter()
+        .fold(Score::ZERO, |score, (kind, bonus)| {
+            let outposts = state.pieces(color, kind).into_iter().filter(|&sq| {
+                let rank = sq.rank().relative(color).idx();
+                let defended = masks::PAWN_ATTACKS[(!color).idx()][sq.idx()] & own_pawns;
+                let attackers = masks::PASSED_PAWN_SPANS[color.idx()][sq.idx()]
+                    & masks::ADJACENT_FILES[sq.file().idx()]
+                    & enemy_pawns;
+                (3..=5).contains(&rank) && !defended.is_empty() && attackers.is_empty()
+            });
+            score + bonus * outposts.count() as i32
+        })
+}
+
+/// Score the given side's threats against enemy pieces.
+pub fn threats(state: &GameState, color: Color, attacks: &Attacks) -> Score {
+    let enemy = !color;
+    let enemy_pieces = state.side_pieces(enemy)
+        & !state.pieces(enemy, PieceType::Pawn)
+        & !state.pieces(enemy, PieceType::King);
+    let enemy_majors = state.pieces(enemy, PieceType::Rook) | state.pieces(enemy, PieceType::Queen);
+    let minor_attacks =
+        attacks.get(color, PieceType::Knight) | attacks.get(color, PieceType::Bishop);
+
+    let by_pawn = enemy_pieces & attacks.get(color, PieceType::Pawn);
+    let by_minor = enemy_majors & minor_attacks;
+    let by_rook = state.pieces(enemy, PieceType::Queen) & attacks.get(color, PieceType::Rook);
+    let hanging = enemy_pieces & attacks.all[color.idx()] & !attacks.all[enemy.idx()];
+
+    THREAT_BY_PAWN * by_pawn.popcount() as i32
+        + THREAT_BY_MINOR * by_minor.popcount() as i32
+        + THREAT_BY_ROOK * by_rook.popcount() as i32
+        + HANGING * hanging.popcount() as i32
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    #[test]
+    fn test_mobility() {
+        // The a1 knight reaches b3 and c2, but b3 is covered by the a4 pawn.
+        let state = parse_fen("4k3/8/8/8/p7/8/8/N3K3 w - - 0 1");
+        let attacks = Attacks::new(&state);
+        assert_eq!(mobility(&state, Color::White, &attacks), Score::new(4, 4) * (1 - 4));
+
+        // The e4 rook attacks 14 squares, one of which holds its own king.
+        let state = parse_fen("4k3/8/8/8/4R3/8/8/4K3 w - - 0 1");
+        let attacks = Attacks::new(&state);
+        assert_eq!(mobility(&state, Color::White, &attacks), Score::new(2, 4) * (13 - 7));
+    }
+
+    #[test]
+    fn test_bishop_pair_and_rook_files() {
+        let state = parse_fen("r3k2r/ppp4p/8/8/8/8/PP3PPP/2B1KB1R w - - 0 1");
+        assert_eq!(bishop_pair(&state, Color::White), BISHOP_PAIR);
+        assert_eq!(bishop_pair(&state, Color::Black), Score::ZERO);
+        assert_eq!(rook_files(&state, Color::White), Score::ZERO);
+        assert_eq!(rook_files(&state, Color::Black), Score::ZERO);
+
+        let state = parse_fen("3rk2r/p6p/8/8/8/8/1P3PP1/3RK2R w - - 0 1");
+        assert_eq!(rook_files(&state, Color::White), ROOK_OPEN_FILE + ROOK_SEMI_OPEN_FILE);
+        assert_eq!(rook_files(&state, Color::Black), ROOK_OPEN_FILE);
+    }
+
+    #[test]
+    fn test_outposts() {
+        // d5 is defended by e4 and no black pawn can attack it; f5 is defended by e4 but the g7
+        // pawn can chase it away.
+        let state = parse_fen("4k3/6p1/8/3N1N2/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(outposts(&state, Color::White), KNIGHT_OUTPOST);
+    }
+
+    #[test]
+    fn test_threats() {
+        // The e4 pawn hits the d5 knight, which is also undefended. The c3 knight hits the a4
+        // rook, which the b5 pawn defends.
+        let state = parse_fen("4k3/8/8/1p1n4/r3P3/2N5/8/4K3 w - - 0 1");
+        let attacks = Attacks::new(&state);
+        assert_eq!(
+            threats(&state, Color::White, &attacks),
+            THREAT_BY_PAWN + THREAT_BY_MINOR + HANGING,
+        );
+    }
+}
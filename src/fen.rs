@@ -1,6 +1,11 @@
-///! This file contains functions to parse FEN strings into GameStates and to serialize GameStates
-///! into FEN strings.
-use crate::game_state::{ GameState, Square, Piece, sq_utils };
+//! This file contains functions to parse FEN strings into GameStates and to serialize GameStates
+//! into FEN strings.
+//!
+//! Chess960 castle rights are supported in both X-FEN (KQkq, with a rook file letter only where
+//! KQkq would be ambiguous) and Shredder-FEN (always rook file letters, e.g. HAha).
+use crate::bits::bitboard::Bitboard;
+use crate::game_state::{ CastleRights, GameState, Piece, PieceType, Color };
+use crate::square::{ Square, File, Rank };
 
 
 /// Used to conver between FEN and GameState reprs.
@@ -18,97 +23,141 @@ const FEN_PIECES: [(char, Piece);12] = [
     ('q', Piece::BlackQueen),
     ('k', Piece::BlackKing),
 ];
-const FEN_RANKS: [(char, u8);8] = [
-    ('1', 0),
-    ('2', 1),
-    ('3', 2),
-    ('4', 3),
-    ('5', 4),
-    ('6', 5),
-    ('7', 6),
-    ('8', 7),
-];
-const FEN_FILES: [(char, u8);8] = [
-    ('a', 0),
-    ('b', 1),
-    ('c', 2),
-    ('d', 3),
-    ('e', 4),
-    ('f', 5),
-    ('g', 6),
-    ('h', 7),
-];
 
+/// Make a GameState from the given FEN string. Panics if the FEN string is malformed; use
+/// `try_parse_fen` for FEN strings from outside the program.
+pub fn parse_fen(fen: &str) -> GameState {
+    try_parse_fen(fen).unwrap_or_else(|e| panic!("{}", e))
+}
 
-/// Make a GameState from the given FEN string.
-pub fn parse_fen(fen: &String) -> GameState {
-    let fields: Vec<&str> = fen.split(" ").collect();
-    if fields.len() != 6 { panic!("Invalid FEN string.") }
+/// Make a GameState from the given FEN string, or describe why it is malformed.
+///
+/// Only the syntax of the FEN string is checked here. Whether the position makes sense (e.g. that
+/// each side has one king) is up to `GameState::validate` or the variant being played.
+pub fn try_parse_fen(fen: &str) -> Result<GameState, String> {
+    let fields: Vec<&str> = fen.split(' ').collect();
+    if fields.len() != 6 { return Err(format!("Invalid FEN string: {}", fen)) }
     let pos_str = fields[0];
 
     let to_move_str = fields[1];
     let castle_str = fields[2];
     let ep_str = fields[3];
-    let fullmove: u32 = fields[4].trim().parse().expect("Fullmove is not a string.");
-    let halfmove: u8 = fields[5].trim().parse().expect("Halfmove is not a string.");
+    let halfmove: u8 = fields[4].parse()
+        .map_err(|_| format!("Invalid halfmove clock: {}", fields[4]))?;
+    let fullmove: u32 = fields[5].parse()
+        .map_err(|_| format!("Invalid fullmove number: {}", fields[5]))?;
 
     let mut game_state = GameState::new(
-        [0;12],
-        parse_utils::white_to_move_from(to_move_str).expect("Could not parse player to move."),
-        parse_utils::ep_square_from(ep_str),
+        [Bitboard::EMPTY; 12],
+        to_move_str.parse()?,
+        None,
         halfmove,
         fullmove,
-        parse_utils::castlerights_from(castle_str),
+        CastleRights::NONE,
     );
 
-    parse_utils::add_pieces(pos_str, &mut game_state);
-    game_state
+    parse_utils::add_pieces(pos_str, &mut game_state)?;
+    game_state.set_ep_square(parse_utils::ep_square_from(ep_str)?);
+    parse_utils::add_castlerights(castle_str, &mut game_state)?;
+    Ok(game_state)
 }
 
 
-/// Make a FEN string from the given GameState.
+/// Make a FEN string from the given GameState. Castle rights are written in X-FEN, which is the
+/// same as standard FEN for standard start positions.
 pub fn to_fen(game_state: &GameState) -> String {
-    vec![
+    ser_fields(game_state, false)
+}
+
+/// Make a Shredder-FEN string from the given GameState, which writes castle rights as rook files.
+pub fn to_shredder_fen(game_state: &GameState) -> String {
+    ser_fields(game_state, true)
+}
+
+
+fn ser_fields(game_state: &GameState, shredder: bool) -> String {
+    [
         serialize_utils::ser_bbs(game_state),
         serialize_utils::ser_side_to_move(game_state),
-        serialize_utils::ser_castle_rights(game_state),
+        serialize_utils::ser_castle_rights(game_state, shredder),
         serialize_utils::ser_ep_square(game_state),
-        serialize_utils::ser_fullmove_clock(game_state),
         serialize_utils::ser_halfmove_clock(game_state),
+        serialize_utils::ser_fullmove_clock(game_state),
     ].join(" ")
 }
 
 
+/// Get the FEN character for the given piece.
+pub(crate) fn char_from_piece(p: Piece) -> Option<char> {
+    FEN_PIECES.iter().find(|(_, piece)| *piece == p).map(|(c, _)| *c)
+}
+
+/// Get the piece for the given FEN character.
+pub(crate) fn piece_from_char(c: char) -> Option<Piece> {
+    FEN_PIECES.iter().find(|(ch, _)| *ch == c).map(|(_, p)| *p)
+}
+
+/// Get the file of the outermost rook of the given side on its back rank, on the kingside or
+/// queenside of the given king file. These are the rooks meant by K and Q in X-FEN.
+fn outermost_rook_file(
+    game_state: &GameState,
+    color: Color,
+    king_file: File,
+    kingside: bool,
+) -> Option<File> {
+    let back_rank = Rank::R1.relative(color);
+    let rook_files = game_state.pieces(color, PieceType::Rook)
+        .into_iter()
+        .filter(|sq| sq.rank() == back_rank)
+        .map(|sq| sq.file());
+    if kingside {
+        rook_files.filter(|&file| file > king_file).max()
+    } else {
+        rook_files.filter(|&file| file < king_file).min()
+    }
+}
+
+
 /// Utility functions for serializing GameState to a FEN string.
 mod serialize_utils {
     use super::*;
 
-    /// Create the FEN field for castle rights from the GameState.
-    pub fn ser_castle_rights(game_state: &GameState) -> String {
+    /// Create the FEN field for castle rights from the GameState, in X-FEN or Shredder-FEN.
+    pub fn ser_castle_rights(game_state: &GameState, shredder: bool) -> String {
         let mut result = String::new();
-        if game_state.castlerights[0] { result.push_str("K") }
-        if game_state.castlerights[1] { result.push_str("Q") }
-        if game_state.castlerights[2] { result.push_str("k") }
-        if game_state.castlerights[3] { result.push_str("q") }
+        for (right, side_char) in ['K', 'Q', 'k', 'q'].into_iter().enumerate() {
+            if !game_state.castlerights.has(right) { continue }
+            let color = if right < 2 { Color::White } else { Color::Black };
+            let kingside = right % 2 == 0;
+            let king_file = game_state.castle_files().kings[color.idx()];
+            let rook_file = game_state.castle_files().rooks[right];
+            let default_file = if kingside { File::H } else { File::A };
+
+            let outermost = outermost_rook_file(game_state, color, king_file, kingside);
+            let unambiguous = outermost.unwrap_or(default_file) == rook_file;
+            let rook_char = if !shredder && unambiguous {
+                side_char
+            } else {
+                let file_char = rook_file.to_string().chars().next().unwrap();
+                if color == Color::White { file_char.to_ascii_uppercase() } else { file_char }
+            };
+            result.push(rook_char);
+        }
+        if result.is_empty() { result.push('-') }
         result
     }
 
     /// Create the FEN field for side-to-move from the GameState.
     pub fn ser_side_to_move(game_state: &GameState) -> String {
-        match game_state.white_to_move {
-            true => String::from("w"),
-            false => String::from("b"),
-        }
+        game_state.side_to_move.to_string()
     }
 
     /// Create the FEN field for the en-passant square from the GameState.
     pub fn ser_ep_square(game_state: &GameState) -> String {
-        if let None = game_state.ep_square { return String::from("-") }
-
-        let ep_square = game_state.ep_square.unwrap();
-        let file_idx: u8 = sq_utils::file_idx(ep_square);
-        let rank_idx: u8 = sq_utils::rank_idx(ep_square);
-        format!("{}{}", char_from_file(file_idx).unwrap(), char_from_rank(rank_idx).unwrap())
+        match game_state.ep_square {
+            None => String::from("-"),
+            Some(sq) => sq.to_string(),
+        }
     }
 
     /// Create the FEN field for the halfmove clock from the GameState.
@@ -124,11 +173,11 @@ mod serialize_utils {
     /// Create the FEN field for the board position from the GameState('s bitboards).
     pub fn ser_bbs(game_state: &GameState) -> String {
         let mut result = String::new();
-        for rank_idx in (0..8).rev() {
+        for rank in Rank::all().rev() {
             let mut cur_empty_count = 0;
-            for file_idx in 0..8 {
-                let sq_idx = sq_utils::square_idx(rank_idx, file_idx);
-                match game_state.occupying_piece(sq_idx) {
+            for file in File::all() {
+                let sq = Square::from_file_rank(file, rank);
+                match game_state.occupying_piece(sq) {
                     Some(piece) => {
                         let piece_char = char_from_piece(piece).unwrap();
                         if cur_empty_count > 0 {
@@ -139,37 +188,14 @@ mod serialize_utils {
                     },
                     None => cur_empty_count += 1,
                 }
-                if file_idx == 7 && cur_empty_count > 0 {
+                if file == File::H && cur_empty_count > 0 {
                     result.push_str(&format!("{}", cur_empty_count));
                 }
             }
-            if rank_idx != 0 { result.push('/') }
+            if rank != Rank::R1 { result.push('/') }
         }
         result
     }
-
-
-    fn char_from_piece(p: Piece) -> Option<char> {
-        for i in 0..FEN_PIECES.len() {
-            if FEN_PIECES[i].1 == p { return Some(FEN_PIECES[i].0) }
-        }
-        None
-    }
-
-    fn char_from_file(file: u8) -> Option<char> {
-        for i in 0..FEN_FILES.len() {
-            if FEN_FILES[i].1 == file { return Some(FEN_FILES[i].0) }
-        }
-        None
-    }
-
-    fn char_from_rank(rank: u8) -> Option<char> {
-        for i in 0..FEN_FILES.len() {
-            if FEN_RANKS[i].1 == rank { return Some(FEN_RANKS[i].0) }
-        }
-        None
-    }
-
 }
 
 
@@ -177,84 +203,82 @@ mod serialize_utils {
 mod parse_utils {
     use super::*;
 
-    /// Get the white_to_move bool from the FEN side to move string.
-    pub fn white_to_move_from(to_move_str: &str) -> Result<bool, String> {
-        if to_move_str == "w" {
-            Ok(true)
-        } else if to_move_str == "b" {
-            Ok(false)
-        } else {
-            Err("Invalid to move field.".to_string())
+    /// Set the castle rights and castle files from the FEN castle string, which may be standard,
+    /// X-FEN or Shredder-FEN. Must be called after the pieces are added.
+    pub fn add_castlerights(castle_str: &str, game_state: &mut GameState) -> Result<(), String> {
+        if castle_str == "-" { return Ok(()) }
+        let invalid = || format!("Invalid castle rights: {}", castle_str);
+        let mut castlerights = game_state.castlerights;
+        let mut castle_files = game_state.castle_files();
+        for c in castle_str.chars() {
+            let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+            let back_rank = Rank::R1.relative(color);
+            let king_file = game_state.pieces(color, PieceType::King)
+                .into_iter()
+                .find(|sq| sq.rank() == back_rank)
+                .map_or(File::E, |sq| sq.file());
+
+            let (kingside, rook_file) = match c.to_ascii_lowercase() {
+                'k' => {
+                    let file = outermost_rook_file(game_state, color, king_file, true);
+                    (true, file.unwrap_or(File::H))
+                },
+                'q' => {
+                    let file = outermost_rook_file(game_state, color, king_file, false);
+                    (false, file.unwrap_or(File::A))
+                },
+                file_char => {
+                    let file: File = file_char.to_string().parse().map_err(|_| invalid())?;
+                    if file == king_file { return Err(invalid()) }
+                    (file > king_file, file)
+                },
+            };
+
+            let right = 2 * color.idx() + if kingside { 0 } else { 1 };
+            if castlerights.has(right) { return Err(invalid()) }
+            castlerights.insert(right);
+            castle_files.kings[color.idx()] = king_file;
+            castle_files.rooks[right] = rook_file;
         }
-    }
-
-    /// Get the castlerights bool array from the FEN castle string.
-    pub fn castlerights_from(castle_str: &str) -> [bool;4] {
-        [
-            castle_str.contains("K"),
-            castle_str.contains("Q"),
-            castle_str.contains("k"),
-            castle_str.contains("q"),
-        ]
+        game_state.set_castlerights(castlerights);
+        game_state.set_castle_files(castle_files);
+        Ok(())
     }
 
     /// Add the pieces to the board from the position string.
-    pub fn add_pieces(pos_str: &str, game_state: &mut GameState) {
-        let mut ranks: Vec<&str> = pos_str.split("/").collect();
-        for i in 0..4 { ranks.swap(i, 7-i) }
-        if ranks.len() != 8 { panic!("Invalid number of ranks.") }
-
-        for i in 0..8 {
-            let mut j = 0;
-            let rank = ranks[i];
+    pub fn add_pieces(pos_str: &str, game_state: &mut GameState) -> Result<(), String> {
+        let invalid = |reason: &str| format!("Invalid board ({}): {}", reason, pos_str);
+        let ranks: Vec<&str> = pos_str.split('/').collect();
+        if ranks.len() != 8 { return Err(invalid("need 8 ranks")) }
+
+        for (rank, rank_str) in Rank::all().rev().zip(ranks) {
             let mut file_idx = 0;
-            while j < rank.len() {
-                let rank_char = format!("{}", ranks[i].chars().nth(j).unwrap());
-                match rank_char.parse::<usize>() {
-                    Ok(num) => {
-                        file_idx += num;
-                        j += 1;
-                    }
-                    _ => {
-                        let piece = piece_from_char(rank_char.chars().nth(0).unwrap()).unwrap();
-                        game_state.add_piece(piece, sq_utils::square_idx(i as u8, file_idx as u8));
+            for c in rank_str.chars() {
+                match c.to_digit(10) {
+                    Some(num @ 1..=8) => file_idx += num as usize,
+                    Some(_) => return Err(invalid("empty squares must be 1 to 8")),
+                    None => {
+                        let piece = piece_from_char(c).ok_or_else(|| invalid("unknown piece"))?;
+                        if file_idx >= 8 { return Err(invalid("rank is too long")) }
+                        let sq = Square::from_file_rank(File::new(file_idx as u8), rank);
+                        game_state.add_piece(piece, sq);
                         file_idx += 1;
-                        j += 1;
                     },
                 }
             }
+            if file_idx != 8 { return Err(invalid("each rank needs 8 squares")) }
         }
-
+        Ok(())
     }
 
     /// Get the ep square from the ep string.
-    pub fn ep_square_from(ep_str: &str) -> Option<Square> {
-        if ep_str == "-" { return None }
-        let file_idx = file_from_char(ep_str.chars().nth(0).unwrap()).unwrap();
-        let rank_idx = rank_from_char(ep_str.chars().nth(1).unwrap()).unwrap();
-        Some(sq_utils::square_idx(rank_idx, file_idx))
-    }
-
-
-    fn piece_from_char(c: char) -> Option<Piece> {
-        for i in 0..FEN_PIECES.len() {
-            if FEN_PIECES[i].0 == c { return Some(FEN_PIECES[i].1) }
-        }
-        None
-    }
-
-    fn file_from_char(c: char) -> Option<u8> {
-        for i in 0..FEN_FILES.len() {
-            if FEN_FILES[i].0 == c { return Some(FEN_FILES[i].1) }
-        }
-        None
-    }
-
-    fn rank_from_char(c: char) -> Option<u8> {
-        for i in 0..FEN_FILES.len() {
-            if FEN_RANKS[i].0 == c { return Some(FEN_RANKS[i].1) }
+    pub fn ep_square_from(ep_str: &str) -> Result<Option<Square>, String> {
+        if ep_str == "-" { return Ok(None) }
+        let sq: Square = ep_str.parse()?;
+        if sq.rank() != Rank::R3 && sq.rank() != Rank::R6 {
+            return Err(format!("Invalid ep square: {}", ep_str));
         }
-        None
+        Ok(Some(sq))
     }
 }
 
@@ -267,48 +291,100 @@ mod tests {
     #[test]
     /// Test that the starting FEN is parsed correctly.
     fn test_parse_starting_position() {
-        let game_state = parse_fen(&STARTING_FEN.to_string());
-        assert_eq!(game_state.occupying_piece(0  as u8).unwrap(), Piece::WhiteRook);
-        assert_eq!(game_state.occupying_piece(1  as u8).unwrap(), Piece::WhiteKnight);
-        assert_eq!(game_state.occupying_piece(2  as u8).unwrap(), Piece::WhiteBishop);
-        assert_eq!(game_state.occupying_piece(3  as u8).unwrap(), Piece::WhiteQueen);
-        assert_eq!(game_state.occupying_piece(4  as u8).unwrap(), Piece::WhiteKing);
-        assert_eq!(game_state.occupying_piece(5  as u8).unwrap(), Piece::WhiteBishop);
-        assert_eq!(game_state.occupying_piece(6  as u8).unwrap(), Piece::WhiteKnight);
-        assert_eq!(game_state.occupying_piece(7  as u8).unwrap(), Piece::WhiteRook);
-
-        assert_eq!(game_state.occupying_piece(8  as u8).unwrap(), Piece::WhitePawn);
-        assert_eq!(game_state.occupying_piece(9  as u8).unwrap(), Piece::WhitePawn);
-        assert_eq!(game_state.occupying_piece(10 as u8).unwrap(), Piece::WhitePawn);
-        assert_eq!(game_state.occupying_piece(11 as u8).unwrap(), Piece::WhitePawn);
-        assert_eq!(game_state.occupying_piece(12 as u8).unwrap(), Piece::WhitePawn);
-        assert_eq!(game_state.occupying_piece(13 as u8).unwrap(), Piece::WhitePawn);
-        assert_eq!(game_state.occupying_piece(14 as u8).unwrap(), Piece::WhitePawn);
-        assert_eq!(game_state.occupying_piece(15 as u8).unwrap(), Piece::WhitePawn);
+        let game_state = parse_fen(STARTING_FEN);
+        assert_eq!(game_state.occupying_piece(Square::new(0)).unwrap(), Piece::WhiteRook);
+        assert_eq!(game_state.occupying_piece(Square::new(1)).unwrap(), Piece::WhiteKnight);
+        assert_eq!(game_state.occupying_piece(Square::new(2)).unwrap(), Piece::WhiteBishop);
+        assert_eq!(game_state.occupying_piece(Square::new(3)).unwrap(), Piece::WhiteQueen);
+        assert_eq!(game_state.occupying_piece(Square::new(4)).unwrap(), Piece::WhiteKing);
+        assert_eq!(game_state.occupying_piece(Square::new(5)).unwrap(), Piece::WhiteBishop);
+        assert_eq!(game_state.occupying_piece(Square::new(6)).unwrap(), Piece::WhiteKnight);
+        assert_eq!(game_state.occupying_piece(Square::new(7)).unwrap(), Piece::WhiteRook);
+
+        assert_eq!(game_state.occupying_piece(Square::new(8)).unwrap(), Piece::WhitePawn);
+        assert_eq!(game_state.occupying_piece(Square::new(9)).unwrap(), Piece::WhitePawn);
+        assert_eq!(game_state.occupying_piece(Square::new(10)).unwrap(), Piece::WhitePawn);
+        assert_eq!(game_state.occupying_piece(Square::new(11)).unwrap(), Piece::WhitePawn);
+        assert_eq!(game_state.occupying_piece(Square::new(12)).unwrap(), Piece::WhitePawn);
+        assert_eq!(game_state.occupying_piece(Square::new(13)).unwrap(), Piece::WhitePawn);
+        assert_eq!(game_state.occupying_piece(Square::new(14)).unwrap(), Piece::WhitePawn);
+        assert_eq!(game_state.occupying_piece(Square::new(15)).unwrap(), Piece::WhitePawn);
 
         for i in 16..48 {
-            assert_eq!(game_state.occupying_piece(i as u8), None);
+            assert_eq!(game_state.occupying_piece(Square::new(i)), None);
         }
 
-        assert_eq!(game_state.occupying_piece(48 as u8).unwrap(), Piece::BlackPawn);
-        assert_eq!(game_state.occupying_piece(49 as u8).unwrap(), Piece::BlackPawn);
-        assert_eq!(game_state.occupying_piece(50 as u8).unwrap(), Piece::BlackPawn);
-        assert_eq!(game_state.occupying_piece(51 as u8).unwrap(), Piece::BlackPawn);
-        assert_eq!(game_state.occupying_piece(52 as u8).unwrap(), Piece::BlackPawn);
-        assert_eq!(game_state.occupying_piece(53 as u8).unwrap(), Piece::BlackPawn);
-        assert_eq!(game_state.occupying_piece(54 as u8).unwrap(), Piece::BlackPawn);
-        assert_eq!(game_state.occupying_piece(55 as u8).unwrap(), Piece::BlackPawn);
+        assert_eq!(game_state.occupying_piece(Square::new(48)).unwrap(), Piece::BlackPawn);
+        assert_eq!(game_state.occupying_piece(Square::new(49)).unwrap(), Piece::BlackPawn);
+        assert_eq!(game_state.occupying_piece(Square::new(50)).unwrap(), Piece::BlackPawn);
+        assert_eq!(game_state.occupying_piece(Square::new(51)).unwrap(), Piece::BlackPawn);
+        assert_eq!(game_state.occupying_piece(Square::new(52)).unwrap(), Piece::BlackPawn);
+        assert_eq!(game_state.occupying_piece(Square::new(53)).unwrap(), Piece::BlackPawn);
+        assert_eq!(game_state.occupying_piece(Square::new(54)).unwrap(), Piece::BlackPawn);
+        assert_eq!(game_state.occupying_piece(Square::new(55)).unwrap(), Piece::BlackPawn);
         
-        assert_eq!(game_state.occupying_piece(56 as u8).unwrap(), Piece::BlackRook);
-        assert_eq!(game_state.occupying_piece(57 as u8).unwrap(), Piece::BlackKnight);
-        assert_eq!(game_state.occupying_piece(58 as u8).unwrap(), Piece::BlackBishop);
-        assert_eq!(game_state.occupying_piece(59 as u8).unwrap(), Piece::BlackQueen);
-        assert_eq!(game_state.occupying_piece(60 as u8).unwrap(), Piece::BlackKing);
-        assert_eq!(game_state.occupying_piece(61 as u8).unwrap(), Piece::BlackBishop);
-        assert_eq!(game_state.occupying_piece(62 as u8).unwrap(), Piece::BlackKnight);
-        assert_eq!(game_state.occupying_piece(63 as u8).unwrap(), Piece::BlackRook);
+        assert_eq!(game_state.occupying_piece(Square::new(56)).unwrap(), Piece::BlackRook);
+        assert_eq!(game_state.occupying_piece(Square::new(57)).unwrap(), Piece::BlackKnight);
+        assert_eq!(game_state.occupying_piece(Square::new(58)).unwrap(), Piece::BlackBishop);
+        assert_eq!(game_state.occupying_piece(Square::new(59)).unwrap(), Piece::BlackQueen);
+        assert_eq!(game_state.occupying_piece(Square::new(60)).unwrap(), Piece::BlackKing);
+        assert_eq!(game_state.occupying_piece(Square::new(61)).unwrap(), Piece::BlackBishop);
+        assert_eq!(game_state.occupying_piece(Square::new(62)).unwrap(), Piece::BlackKnight);
+        assert_eq!(game_state.occupying_piece(Square::new(63)).unwrap(), Piece::BlackRook);
 
         let serialized = to_fen(&game_state);
         assert_eq!(STARTING_FEN, serialized);
     }
+
+    #[test]
+    /// Test that the halfmove clock comes before the fullmove number.
+    fn test_clocks() {
+        let fen = "4k3/8/8/8/8/8/8/4K3 b - - 12 40";
+        let game_state = parse_fen(fen);
+        assert_eq!(game_state.halfmove_clock, 12);
+        assert_eq!(game_state.fullmove_clock, 40);
+        assert_eq!(to_fen(&game_state), fen);
+    }
+
+    #[test]
+    /// Test that Chess960 castle rights are parsed from and written to X-FEN and Shredder-FEN.
+    fn test_chess960_castle_rights() {
+        let shredder = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1";
+        let game_state = parse_fen(shredder);
+        assert_eq!(to_fen(&game_state), STARTING_FEN);
+        assert_eq!(to_shredder_fen(&game_state), shredder);
+        assert_eq!(to_shredder_fen(&parse_fen(STARTING_FEN)), shredder);
+
+        // The f8 rook is not the outermost kingside rook, so X-FEN needs its file.
+        let x_fen = "1r1k1r1r/8/8/8/8/8/8/RK4R1 w KQfq - 0 1";
+        let game_state = parse_fen(x_fen);
+        assert_eq!(game_state.castlerights, CastleRights::ALL);
+        assert_eq!(game_state.castle_files().kings, [File::B, File::D]);
+        assert_eq!(game_state.castle_files().rooks, [File::G, File::A, File::F, File::B]);
+        assert_eq!(to_fen(&game_state), x_fen);
+        assert_eq!(to_shredder_fen(&game_state), "1r1k1r1r/8/8/8/8/8/8/RK4R1 w GAfb - 0 1");
+        assert_eq!(to_fen(&parse_fen("1r1k1r1r/8/8/8/8/8/8/RK4R1 w GAfb - 0 1")), x_fen);
+    }
+
+    #[test]
+    /// Test that malformed FEN strings are reported rather than panicking.
+    fn test_malformed_fens() {
+        assert!(try_parse_fen(STARTING_FEN).is_ok());
+        for fen in [
+            "x w KQkq - 1 0",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/45/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/0/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkqK - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1",
+        ] {
+            assert!(try_parse_fen(fen).is_err(), "{}", fen);
+        }
+    }
 }
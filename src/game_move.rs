@@ -1,5 +1,6 @@
-///! This file contains structs/types related to moves on a GameState.
-use crate::game_state::{ Square, Piece };
+//! This file contains structs/types related to moves on a GameState.
+use crate::game_state::{ self, Color, GameState, Piece, PieceType };
+use crate::square::{ Square, File, Rank };
 
 
 // Do not change the order!
@@ -11,7 +12,7 @@ const METADATA_TO_MOVETYPE: [MoveType;16] = [
     MoveType::Capture,              // code 4
     MoveType::EpCapture,            // code 5
     MoveType::NullMove,             // code 6
-    MoveType::NullMove,             // code 7
+    MoveType::Drop,                 // code 7
     MoveType::KnightPromo,          // code 8
     MoveType::BishopPromo,          // code 9
     MoveType::RookPromo,            // code 10
@@ -47,6 +48,8 @@ const LSB4_BITMASK: u16 = 15;
 ///  | 3    | 0         | 0        | 1         | 1         | QueenCastle        |
 ///  | 4    | 0         | 1        | 0         | 0         | Capture            |
 ///  | 5    | 0         | 1        | 0         | 1         | EpCapture          |
+///  | 6    | 0         | 1        | 1         | 0         | NullMove           |
+///  | 7    | 0         | 1        | 1         | 1         | Drop               |
 ///  | 8    | 1         | 0        | 0         | 0         | KnightPromo        |
 ///  | 9    | 1         | 0        | 0         | 1         | BishopPromo        |
 ///  | 10   | 1         | 0        | 1         | 0         | RookPromo          |
@@ -56,7 +59,12 @@ const LSB4_BITMASK: u16 = 15;
 ///  | 14   | 1         | 1        | 1         | 0         | RookPromoCapture   |
 ///  | 15   | 1         | 1        | 1         | 1         | QueenPromoCapture  |
 ///
-/// Note: castling fromsquare is king's square, tosquare is castle side's rook square.
+/// Note: castling fromsquare is the king's square and tosquare is the king's destination (e.g.
+/// e1g1), in Chess960 as well. UCI's king-takes-rook form is only used by `from_uci`/`to_uci`.
+///
+/// Drops (Crazyhouse) have no fromsquare, so the from bits hold the dropped PieceType instead. Use
+/// `new_drop` and `drop_kind` rather than `fromsquare` for drops. Codes 6 and 7 have the capture
+/// bit set, but neither is a capture.
 #[derive(Copy, Clone)]
 pub struct GameMove {
     pub data: u16,
@@ -65,10 +73,18 @@ pub struct GameMove {
 impl GameMove {
     /// Create a gamemove from the given fromsquare, tosquare, and move type.
     pub fn new(fromsquare: Square, tosquare: Square, move_type: MoveType) -> Self {
-        let val = ((((0u16 | fromsquare as u16) << 6) | tosquare as u16) << 4) | move_type as u16;
+        let squares = (fromsquare.as_u8() as u16) << 6 | tosquare.as_u8() as u16;
+        let val = squares << 4 | move_type as u16;
         GameMove { data: val }
     }
 
+    /// Create a drop of the given piece type onto the given square.
+    pub fn new_drop(kind: PieceType, tosquare: Square) -> Self {
+        debug_assert!(kind != PieceType::King, "Kings can't be dropped.");
+        let squares = (kind as u16) << 6 | tosquare.as_u8() as u16;
+        GameMove { data: squares << 4 | MoveType::Drop as u16 }
+    }
+
     /// Create a GameMove struct from a u16.
     pub fn from_val(val: u16) -> Self {
         GameMove { data: val }
@@ -76,12 +92,12 @@ impl GameMove {
 
     /// Get the from-square index of this move.
     pub fn fromsquare(&self) -> Square {
-        (self.data >> 10) as u8
+        Square::new((self.data >> 10) as u8)
     }
 
     /// Get the to-square index of this move.
     pub fn tosquare(&self) -> Square {
-        (self.data >> 4 & LSB6_BITMASK) as u8
+        Square::new((self.data >> 4 & LSB6_BITMASK) as u8)
     }
 
     /// Get the MoveType of this move.
@@ -91,7 +107,18 @@ impl GameMove {
 
     /// Returns whether this move is a capture.
     pub fn is_capture(&self) -> bool {
+        let move_type = self.move_type();
         IS_CAPTURE_MASK & self.data != 0
+            && move_type != MoveType::NullMove
+            && move_type != MoveType::Drop
+    }
+
+    /// Get the type of piece dropped by this move, or None if it is not a drop.
+    pub fn drop_kind(&self) -> Option<PieceType> {
+        if self.move_type() != MoveType::Drop {
+            return None;
+        }
+        PieceType::all().nth((self.data >> 10) as usize)
     }
 
     /// Returns whether this move is a promotion.
@@ -99,24 +126,146 @@ impl GameMove {
         IS_PROMO_MASK & self.data != 0
     }
 
-    /// Get the piece which is to be promoted.
-    pub fn promo_piece(&self, white_to_move: bool) -> Option<Piece> {
+    /// Get the type of piece which is to be promoted to.
+    pub fn promo_kind(&self) -> Option<PieceType> {
         if !self.is_promo() {
             return None;
         }
 
-        return match self.data & 3u16 {
-            KNIGHT_PROMO_MASK => 
-                Some(if white_to_move { Piece::WhiteKnight } else { Piece::BlackKnight }),
-            BISHOP_PROMO_MASK =>
-                Some(if white_to_move { Piece::WhiteBishop } else { Piece::BlackBishop }),
-            ROOK_PROMO_MASK =>
-                Some(if white_to_move { Piece::WhiteRook } else { Piece::BlackRook }),
-            QUEEN_PROMO_MASK =>
-                Some(if white_to_move { Piece::WhiteQueen } else { Piece::BlackQueen }),
+        match self.data & 3u16 {
+            KNIGHT_PROMO_MASK => Some(PieceType::Knight),
+            BISHOP_PROMO_MASK => Some(PieceType::Bishop),
+            ROOK_PROMO_MASK => Some(PieceType::Rook),
+            QUEEN_PROMO_MASK => Some(PieceType::Queen),
             _ => None,
         }
     }
+
+    /// Get the piece which is to be promoted to, for the given side to move.
+    pub fn promo_piece(&self, color: Color) -> Option<Piece> {
+        self.promo_kind().map(|kind| Piece::new(color, kind))
+    }
+
+    /// Parse a move in UCI long algebraic notation (e.g. "e2e4", "e7e8q", or "N@f3" for a drop)
+    /// for the side to move in the given GameState. The move type is worked out from the board.
+    /// Castling is written as the king moving two files (e1g1), or as the king taking its own
+    /// rook (e1h1) if chess960 is set, and is stored as the king moving to its destination.
+    ///
+    /// Only checks that the move is well-formed for the position, not that it is legal.
+    pub fn from_uci(uci: &str, state: &GameState, chess960: bool) -> Result<Self, String> {
+        let invalid = || format!("Invalid move: {}", uci);
+        let color = state.side_to_move;
+
+        if let Some((kind_str, sq_str)) = uci.split_once('@') {
+            let kind = match kind_str {
+                "P" => PieceType::Pawn,
+                "N" => PieceType::Knight,
+                "B" => PieceType::Bishop,
+                "R" => PieceType::Rook,
+                "Q" => PieceType::Queen,
+                _ => return Err(invalid()),
+            };
+            let tosquare: Square = sq_str.parse().map_err(|_| invalid())?;
+            if state.occupying_piece(tosquare).is_some() { return Err(invalid()) }
+            return Ok(GameMove::new_drop(kind, tosquare));
+        }
+
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) { return Err(invalid()) }
+        let fromsquare: Square = uci[0..2].parse().map_err(|_| invalid())?;
+        let tosquare: Square = uci[2..4].parse().map_err(|_| invalid())?;
+        let moving = state.occupying_piece(fromsquare)
+            .filter(|piece| piece.color() == color)
+            .ok_or_else(invalid)?;
+        let captured = state.occupying_piece(tosquare);
+
+        // Castling: the king moves two files from its home square, or takes its own castling rook
+        // in Chess960.
+        let castle_files = state.castle_files();
+        if moving.kind() == PieceType::King && fromsquare == castle_files.king_square(color) {
+            let kingside = tosquare.file() > fromsquare.file();
+            let move_type = if kingside { MoveType::KingCastle } else { MoveType::QueenCastle };
+            let is_castle = if chess960 {
+                let right = game_state::castle_right_idx(move_type, color);
+                tosquare == castle_files.rook_square(right)
+                    && captured == Some(Piece::new(color, PieceType::Rook))
+            } else {
+                let target_file = if kingside { File::G } else { File::C };
+                tosquare.rank() == fromsquare.rank()
+                    && tosquare.file() == target_file
+                    && fromsquare.file().distance(tosquare.file()) == 2
+            };
+            if is_castle {
+                let (king_from, king_to, _, _) = state.castle_squares(move_type, color);
+                return Ok(GameMove::new(king_from, king_to, move_type));
+            }
+        }
+
+        if captured.is_some_and(|piece| piece.color() == color) { return Err(invalid()) }
+        let is_capture = captured.is_some();
+        let is_pawn = moving.kind() == PieceType::Pawn;
+
+        let promo_kind = match uci[4..].chars().next() {
+            None => None,
+            Some('n') => Some(PieceType::Knight),
+            Some('b') => Some(PieceType::Bishop),
+            Some('r') => Some(PieceType::Rook),
+            Some('q') => Some(PieceType::Queen),
+            Some(_) => return Err(invalid()),
+        };
+        if promo_kind.is_some() && !is_pawn { return Err(invalid()) }
+
+        let move_type = match (promo_kind, is_capture) {
+            (Some(PieceType::Knight), false) => MoveType::KnightPromo,
+            (Some(PieceType::Bishop), false) => MoveType::BishopPromo,
+            (Some(PieceType::Rook), false) => MoveType::RookPromo,
+            (Some(_), false) => MoveType::QueenPromo,
+            (Some(PieceType::Knight), true) => MoveType::KnightPromoCapture,
+            (Some(PieceType::Bishop), true) => MoveType::BishopPromoCapture,
+            (Some(PieceType::Rook), true) => MoveType::RookPromoCapture,
+            (Some(_), true) => MoveType::QueenPromoCapture,
+            (None, true) => MoveType::Capture,
+            (None, false) if is_pawn && fromsquare.file() != tosquare.file() => {
+                if state.ep_square != Some(tosquare) { return Err(invalid()) }
+                MoveType::EpCapture
+            },
+            (None, false) if is_pawn && fromsquare.rank().distance(tosquare.rank()) == 2 =>
+                MoveType::DoublePawnPush,
+            (None, false) => MoveType::Quiet,
+        };
+        Ok(GameMove::new(fromsquare, tosquare, move_type))
+    }
+
+    /// Get this move in UCI long algebraic notation, for the given GameState's castle files.
+    /// Castling is written as the king moving to its destination (e1g1), or as the king taking
+    /// its own rook (e1h1) if chess960 is set.
+    pub fn to_uci(&self, state: &GameState, chess960: bool) -> String {
+        if let Some(kind) = self.drop_kind() {
+            let kind_char = match kind {
+                PieceType::Pawn => 'P',
+                PieceType::Knight => 'N',
+                PieceType::Bishop => 'B',
+                PieceType::Rook => 'R',
+                _ => 'Q',
+            };
+            return format!("{}@{}", kind_char, self.tosquare());
+        }
+        let move_type = self.move_type();
+        let is_castle = move_type == MoveType::KingCastle || move_type == MoveType::QueenCastle;
+        if chess960 && is_castle {
+            let fromsquare = self.fromsquare();
+            let color = if fromsquare.rank() == Rank::R1 { Color::White } else { Color::Black };
+            let right = game_state::castle_right_idx(move_type, color);
+            return format!("{}{}", fromsquare, state.castle_files().rook_square(right));
+        }
+        let promo = match self.promo_kind() {
+            Some(PieceType::Knight) => "n",
+            Some(PieceType::Bishop) => "b",
+            Some(PieceType::Rook) => "r",
+            Some(_) => "q",
+            None => "",
+        };
+        format!("{}{}{}", self.fromsquare(), self.tosquare(), promo)
+    }
 }
 
 
@@ -131,6 +280,7 @@ pub enum MoveType {
     QueenCastle         = 0b0011,
     Capture             = 0b0100,
     EpCapture           = 0b0101,
+    Drop                = 0b0111,
     KnightPromo         = 0b1000,
     BishopPromo         = 0b1001,
     RookPromo           = 0b1010,
@@ -140,3 +290,70 @@ pub enum MoveType {
     RookPromoCapture    = 0b1110,
     QueenPromoCapture   = 0b1111,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    #[test]
+    fn test_drop_encoding() {
+        let drop = GameMove::new_drop(PieceType::Rook, Square::D5);
+        assert!(drop.move_type() == MoveType::Drop);
+        assert_eq!(drop.drop_kind(), Some(PieceType::Rook));
+        assert_eq!(drop.tosquare(), Square::D5);
+        assert!(!drop.is_capture() && !drop.is_promo());
+
+        let capture = GameMove::new(Square::D4, Square::D5, MoveType::Capture);
+        assert!(capture.is_capture());
+        assert_eq!(capture.drop_kind(), None);
+    }
+
+    #[test]
+    fn test_uci_moves() {
+        let state = parse_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        let parse = |uci| {
+            let game_move = GameMove::from_uci(uci, &state, false).unwrap();
+            (game_move.fromsquare(), game_move.tosquare(), game_move.move_type())
+        };
+        assert!(parse("e1g1") == (Square::E1, Square::G1, MoveType::KingCastle));
+        assert!(parse("e1c1") == (Square::E1, Square::C1, MoveType::QueenCastle));
+        assert!(parse("e1f1") == (Square::E1, Square::F1, MoveType::Quiet));
+        assert!(parse("e5d6") == (Square::E5, Square::D6, MoveType::EpCapture));
+        assert!(parse("e5e6") == (Square::E5, Square::E6, MoveType::Quiet));
+        assert!(parse("b7a8n") == (Square::B7, Square::A8, MoveType::KnightPromoCapture));
+        assert!(parse("b7b8q") == (Square::B7, Square::B8, MoveType::QueenPromo));
+        assert!(parse("a1a8") == (Square::A1, Square::A8, MoveType::Capture));
+
+        let invalid = [
+            "e8e7", "e2e4", "a1b1q", "e5f6", "b7b8k", "e1", "h1h9", "K@e4", "P@e5", "e1h1",
+        ];
+        for uci in invalid {
+            assert!(GameMove::from_uci(uci, &state, false).is_err(), "{}", uci);
+        }
+
+        for uci in ["e1g1", "b7a8n", "e5d6", "Q@e4"] {
+            let game_move = GameMove::from_uci(uci, &state, false).unwrap();
+            assert_eq!(game_move.to_uci(&state, false), uci);
+        }
+        let castle = GameMove::from_uci("e1g1", &state, false).unwrap();
+        assert_eq!(castle.to_uci(&state, true), "e1h1");
+    }
+
+    #[test]
+    fn test_chess960_uci_castles() {
+        let state = parse_fen("1r1k1r1r/8/8/8/8/8/8/RK4R1 w KQfq - 0 1");
+        let kingside = GameMove::from_uci("b1g1", &state, true).unwrap();
+        assert!(kingside.fromsquare() == Square::B1 && kingside.tosquare() == Square::G1);
+        assert!(kingside.move_type() == MoveType::KingCastle);
+        let queenside = GameMove::from_uci("b1a1", &state, true).unwrap();
+        assert!(queenside.fromsquare() == Square::B1 && queenside.tosquare() == Square::C1);
+        assert!(queenside.move_type() == MoveType::QueenCastle);
+
+        assert_eq!(kingside.to_uci(&state, true), "b1g1");
+        assert_eq!(queenside.to_uci(&state, true), "b1a1");
+        assert!(GameMove::from_uci("b1g1", &state, false).is_err());
+        assert!(GameMove::from_uci("b1c1", &state, true).unwrap().move_type() == MoveType::Quiet);
+    }
+}
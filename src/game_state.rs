@@ -1,73 +1,115 @@
-///! This file contains structs and types related to the state of the game board.
-use crate::bits::masks;
+//! This file contains structs and types related to the state of the game board.
+use std::fmt;
+use std::ops::{ BitAnd, Not };
+use std::str::FromStr;
+use crate::bits::bitboard::Bitboard;
 use crate::game_move::{ GameMove, MoveType };
+use crate::square::{ Square, File, Rank };
+use crate::variant::{ Variant, VariantUndo, Standard, Outcome };
+use crate::zobrist;
 
 
-/// A BitBoard is a 64-bit unsigned integer which gives piece occupancy. See chessprogrammingwiki
-/// page for more details.
-pub type BitBoard = u64;
+/// A side; the owner of a piece, or the player to move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    White = 0,
+    Black = 1,
+}
 
+impl Color {
+    /// Get the index of this color into GameState's side_bbs.
+    pub const fn idx(self) -> usize {
+        self as usize
+    }
 
-/// Utility functions for BitBoards.
-pub mod bb_utils {
-    use crate::game_state::{ BitBoard, Square };
+    /// Get the other color.
+    pub const fn flip(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
 
-    /// Get whether a given bit is set.
-    pub fn bit_set(bb: BitBoard, sq: Square) -> bool {
-        1 << sq & bb != 0
+    /// Iterate over both colors, white first.
+    pub fn all() -> impl DoubleEndedIterator<Item = Color> {
+        [Color::White, Color::Black].into_iter()
     }
 
-    /// Get index of lsb on the given bitboard.
-    pub fn bitscan(bb: BitBoard) -> u32 {
-        bb.trailing_zeros()
+    /// Get the direction this color's pawns move in, in ranks.
+    pub const fn forward(self) -> i8 {
+        match self {
+            Color::White => 1,
+            Color::Black => -1,
+        }
     }
+}
+
+impl Not for Color {
+    type Output = Color;
 
-    /// Get least significant bit in the given bitboard.
-    pub fn lsb_mask(bb: BitBoard) -> BitBoard {
-        bb & 0u64.wrapping_sub(bb)
+    fn not(self) -> Color {
+        self.flip()
     }
+}
 
-    /// Print a bitboard for debugging.
-    pub fn debug_print(bb: BitBoard) {
-        let mut start: i16 = 56;
-        let mut to_print = String::new();
-        while start >= 0 {
-            for i in start..(start+8) {
-                if bit_set(bb, i as u8) { to_print.push('1') } else { to_print.push('0') }
-            }
-            to_print.push('\n');
-            start -= 8;
+impl fmt::Display for Color {
+    /// Format this color as in the FEN side to move field.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Color::White => write!(f, "w"),
+            Color::Black => write!(f, "b"),
         }
-        println!("{}", to_print);
     }
 }
 
+impl FromStr for Color {
+    type Err = String;
 
-/// Index for a square.
-pub type Square = u8;
+    /// Parse a color from the FEN side to move field.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "w" => Ok(Color::White),
+            "b" => Ok(Color::Black),
+            _ => Err("Invalid to move field.".to_string()),
+        }
+    }
+}
 
-/// Utility functions for squares.
-pub mod sq_utils {
-    use crate::game_state::Square;
-    const LSB3_BITMASK: u64 = 7;
 
-    /// Get the index of a square from its rank and file indices.
-    pub fn square_idx(rank_idx: u8, file_idx: u8) -> Square {
-        (rank_idx << 3) + file_idx
-    }
+/// The kind of a piece, regardless of its color.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PieceType {
+    Pawn = 0,
+    Bishop = 1,
+    Knight = 2,
+    Rook = 3,
+    Queen = 4,
+    King = 5,
+}
 
-    /// Get the file index of a square.
-    /// See Little-Endian Rank-File Mapping @
-    /// www.chessprogramming.org/Square_Mapping_Considerations.
-    pub fn file_idx(square_idx: u8) -> u8 {
-        square_idx & (LSB3_BITMASK as u8)
-    }
+// Do not change the order; indexed by PieceType value.
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
 
-    /// Get the rank index of a square.
-    /// See Little-Endian Rank-File Mapping @
-    /// www.chessprogramming.org/Square_Mapping_Considerations.
-    pub fn rank_idx(square_idx: u8) -> u8 {
-        square_idx >> 3
+impl PieceType {
+    /// The piece types which can be held in a Crazyhouse pocket, indexed by PieceType value.
+    pub const POCKET: [PieceType; 5] = [
+        PieceType::Pawn,
+        PieceType::Bishop,
+        PieceType::Knight,
+        PieceType::Rook,
+        PieceType::Queen,
+    ];
+
+    /// Iterate over all piece types, from pawn to king.
+    pub fn all() -> impl DoubleEndedIterator<Item = PieceType> {
+        PIECE_TYPES.into_iter()
     }
 }
 
@@ -94,41 +136,218 @@ pub enum Piece {
     Null = 12,
 }
 
+// Do not change the order; indexed by [Color][PieceType].
+const PIECES: [[Piece; 6]; 2] = [
+    [
+        Piece::WhitePawn,
+        Piece::WhiteBishop,
+        Piece::WhiteKnight,
+        Piece::WhiteRook,
+        Piece::WhiteQueen,
+        Piece::WhiteKing,
+    ],
+    [
+        Piece::BlackPawn,
+        Piece::BlackBishop,
+        Piece::BlackKnight,
+        Piece::BlackRook,
+        Piece::BlackQueen,
+        Piece::BlackKing,
+    ],
+];
+
+impl Piece {
+    /// Get the piece of the given color and type.
+    pub const fn new(color: Color, kind: PieceType) -> Self {
+        PIECES[color as usize][kind as usize]
+    }
+
+    /// Get the color of this piece. Must not be called on Piece::Null.
+    pub fn color(self) -> Color {
+        debug_assert!(self != Piece::Null, "Null piece has no color.");
+        if (self as u8) < 6 { Color::White } else { Color::Black }
+    }
+
+    /// Get the type of this piece. Must not be called on Piece::Null.
+    pub fn kind(self) -> PieceType {
+        debug_assert!(self != Piece::Null, "Null piece has no type.");
+        PIECE_TYPES[self as usize % 6]
+    }
+
+    /// Iterate over all (non-null) pieces, white pieces first.
+    pub fn all() -> impl DoubleEndedIterator<Item = Piece> {
+        PIECES.into_iter().flatten()
+    }
+
+    /// Get the Unicode chess symbol for this piece. Must not be called on Piece::Null.
+    pub fn symbol(self) -> char {
+        debug_assert!(self != Piece::Null, "Null piece has no symbol.");
+        ['♙', '♗', '♘', '♖', '♕', '♔', '♟', '♝', '♞', '♜', '♛', '♚'][self as usize]
+    }
+}
+
 
-/// Represents a Game; a wrapper around a StateStack of GameStates.
+/// Represents a Game; a GameState which is modified in-place, along with an UndoStack of the
+/// moves applied to it.
 pub struct Game {
-    stack: StateStack,
-    depth_from_start: u8,
+    state: GameState,
+    stack: UndoStack,
+    depth_from_start: usize,
+    variant: Box<dyn Variant>,
 }
 
 impl Game {
-    /// Create a new game, initializing the StateStack with the given starting GameState.
+    /// Create a new game of standard chess starting from the given GameState.
     pub fn new(starting_state: GameState) -> Self {
-        let mut game = Game {
-            stack: StateStack::new(),
+        Game::with_variant(starting_state, Box::new(Standard))
+    }
+
+    /// Create a new game of the given variant starting from the given GameState.
+    pub fn with_variant(starting_state: GameState, variant: Box<dyn Variant>) -> Self {
+        Game {
+            state: starting_state,
+            stack: UndoStack::new(),
             depth_from_start: 0,
-        };
-        game.stack.push(starting_state);
-        game
+            variant,
+        }
     }
 
-    /// Apply the given GameMove to the current state and push the new state to the stack.
+    /// Apply the given GameMove to the current state and push its UndoRecord, along with the
+    /// variant's undo data, to the stack.
     pub fn make(&mut self, game_move: GameMove) {
-        let cur_state = self.stack.peek().unwrap();
-        let next_state = cur_state.make(game_move);
-        self.stack.push(next_state);
+        let undo = self.variant.make(&mut self.state, game_move);
+        debug_assert_eq!(self.variant.validate(&self.state), Ok(()));
+        self.stack.push(undo);
         self.depth_from_start += 1;
     }
 
-    /// Revert this Game to the state before the previous move.
-    pub fn unmake(&mut self) {
-        self.stack.pop();
+    /// Revert this Game to the state before the previous move. Returns an error if no moves have
+    /// been made since the starting GameState.
+    pub fn unmake(&mut self) -> Result<(), String> {
+        let (undo, variant_undo) = self.stack.pop().ok_or("No move to unmake.".to_string())?;
+        self.variant.unmake(&mut self.state, undo, variant_undo);
         self.depth_from_start -= 1;
+        Ok(())
+    }
+
+    /// Get the number of moves made since the starting GameState.
+    pub fn depth_from_start(&self) -> usize {
+        self.depth_from_start
     }
 
     /// Get the current GameState.
     pub fn current_state(&self) -> GameState {
-        *self.stack.peek().unwrap()
+        self.state
+    }
+
+    /// Get the variant being played.
+    pub fn variant(&self) -> &dyn Variant {
+        self.variant.as_ref()
+    }
+
+    /// Get the outcome of the game if it has ended by a rule of its variant.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.variant.outcome(&self.state)
+    }
+}
+
+
+/// Everything needed to revert a GameMove applied in-place with `GameState::make`, apart from what
+/// can be recovered from the board after the move.
+///
+/// Variants keep whatever else they need to undo their own rules: Three-check and Crazyhouse can
+/// work it out from the board and this record, and Atomic returns its explosions as VariantUndo
+/// data, which Game keeps next to this record.
+#[derive(Copy, Clone)]
+pub struct UndoRecord {
+    pub game_move: GameMove,
+    pub captured: Option<Piece>,
+    /// Whether the captured piece was a promoted piece, for Crazyhouse.
+    pub captured_promoted: bool,
+    pub castlerights: CastleRights,
+    pub ep_square: Option<Square>,
+    pub halfmove_clock: u8,
+    pub fullmove_clock: u32,
+    /// The hashes before the move, which unmake restores as they are rather than undoing each
+    /// change to them.
+    pub hash: u64,
+    pub pawn_hash: u64,
+}
+
+
+/// A set of castle rights, with one bit per right in castlerights order: white kingside, white
+/// queenside, black kingside, black queenside.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CastleRights(pub u8);
+
+impl CastleRights {
+    pub const NONE: CastleRights = CastleRights(0);
+    pub const ALL: CastleRights = CastleRights(0b1111);
+
+    /// Check whether the right at the given castlerights index is held.
+    pub const fn has(self, right: usize) -> bool {
+        self.0 & (1 << right) != 0
+    }
+
+    /// Add the right at the given castlerights index.
+    pub fn insert(&mut self, right: usize) {
+        self.0 |= 1 << right;
+    }
+
+    /// Remove the right at the given castlerights index.
+    pub fn remove(&mut self, right: usize) {
+        self.0 &= !(1 << right);
+    }
+}
+
+impl BitAnd for CastleRights {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        CastleRights(self.0 & rhs.0)
+    }
+}
+
+
+/// The home files of each side's king and castling rooks. These are fixed for the whole game: in
+/// standard chess the kings start on the e-file and the rooks on the h- and a-files, while in
+/// Chess960 they depend on the start position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CastleFiles {
+    /// King files, indexed by color.
+    pub kings: [File; 2],
+    /// Rook files, in castlerights order.
+    pub rooks: [File; 4],
+}
+
+impl CastleFiles {
+    pub const STANDARD: CastleFiles = CastleFiles {
+        kings: [File::E, File::E],
+        rooks: [File::H, File::A, File::H, File::A],
+    };
+
+    /// Get the home square of the given side's king.
+    pub fn king_square(&self, color: Color) -> Square {
+        Square::from_file_rank(self.kings[color.idx()], Rank::R1.relative(color))
+    }
+
+    /// Get the home square of the rook for the castle right at the given castlerights index.
+    pub fn rook_square(&self, right: usize) -> Square {
+        let color = if right < 2 { Color::White } else { Color::Black };
+        Square::from_file_rank(self.rooks[right], Rank::R1.relative(color))
+    }
+
+    /// Get, for each square, the castle rights which survive a move from or to that square. Moving
+    /// a king or rook off its home square, or capturing a rook on its home square, loses the
+    /// corresponding rights.
+    pub fn rights_masks(&self) -> [CastleRights; 64] {
+        let mut masks = [CastleRights::ALL; 64];
+        for right in 0..4 {
+            let color = if right < 2 { Color::White } else { Color::Black };
+            masks[self.king_square(color).idx()].remove(right);
+            masks[self.rook_square(right).idx()].remove(right);
+        }
+        masks
     }
 }
 
@@ -139,14 +358,21 @@ impl Game {
 /// See FEN (Forsyth-Edwards) Notation wiki page for more info.
 #[derive(Clone, Copy)]
 pub struct GameState {
-    pub bbs: [BitBoard; 12],
-    pub white_to_move: bool,
-    pub ep_square: Option<Square>, // BitBoard with only en passant square set.
+    pub bbs: [Bitboard; 12],
+    pub side_to_move: Color,
+    pub ep_square: Option<Square>,
     pub halfmove_clock: u8,
     pub fullmove_clock: u32,
-    pub castlerights: [bool; 4], // White/black, kingside and queenside.
-    pub side_bbs: [BitBoard; 2],
-    occupancy: PieceBitBoards,
+    pub castlerights: CastleRights,
+    castle_files: CastleFiles,
+    castle_masks: [CastleRights; 64], // Rebuilt from castle_files by set_castle_files.
+    pub checks_given: [u8; 2], // Checks given by each side, for Three-check.
+    pub pockets: [[u8; 5]; 2], // Pieces in hand by color and PieceType, for Crazyhouse.
+    pub promoted: Bitboard, // Squares holding promoted pieces, for Crazyhouse.
+    pub side_bbs: [Bitboard; 2],
+    occupancy: PieceBitboards,
+    hash: u64,
+    pawn_hash: u64, // The part of hash for the pawns alone, for the pawn hash table.
 }
 
 // Public functions for GameState.
@@ -154,37 +380,209 @@ impl GameState {
     /// Returns a new gamestate with empty bbs, white to move, no ep square, 0 halfmove clock, full
     /// castle rights.
     pub fn new_empty() -> Self {
-        GameState {
-            bbs: [0; 12],
-            white_to_move: true,
-            ep_square: None, // BitBoard with only en passant square set.
+        let mut state = GameState {
+            bbs: [Bitboard::EMPTY; 12],
+            side_to_move: Color::White,
+            ep_square: None,
             halfmove_clock: 0,
             fullmove_clock: 1,
-            castlerights: [true; 4],
-            side_bbs: [0; 2],
-            occupancy: PieceBitBoards::new(),
-        }
+            castlerights: CastleRights::ALL,
+            castle_files: CastleFiles::STANDARD,
+            castle_masks: CastleFiles::STANDARD.rights_masks(),
+            checks_given: [0; 2],
+            pockets: [[0; 5]; 2],
+            promoted: Bitboard::EMPTY,
+            side_bbs: [Bitboard::EMPTY; 2],
+            occupancy: PieceBitboards::new(),
+            hash: 0,
+            pawn_hash: 0,
+        };
+        state.hash = zobrist::hash(&state);
+        state
     }
 
-    /// Make a new GameState.
+    /// Make a new GameState from per-piece bitboards. The side bitboards and square-to-piece map
+    /// are derived from bbs. Castling uses the standard king and rook files; call
+    /// `set_castle_files` afterwards for Chess960 positions.
     pub fn new(
-        bbs: [BitBoard; 12],
-        white_to_move: bool,
+        bbs: [Bitboard; 12],
+        side_to_move: Color,
         ep_square: Option<Square>,
         halfmove_clock: u8,
         fullmove_clock: u32,
-        castlerights: [bool; 4],
+        castlerights: CastleRights,
     ) -> Self {
-        GameState {
-            bbs,
-            white_to_move,
+        let mut state = GameState {
+            bbs: [Bitboard::EMPTY; 12],
+            side_to_move,
             ep_square,
             halfmove_clock,
             fullmove_clock,
             castlerights,
-            side_bbs: [0; 2],
-            occupancy: PieceBitBoards::new(),
+            castle_files: CastleFiles::STANDARD,
+            castle_masks: CastleFiles::STANDARD.rights_masks(),
+            checks_given: [0; 2],
+            pockets: [[0; 5]; 2],
+            promoted: Bitboard::EMPTY,
+            side_bbs: [Bitboard::EMPTY; 2],
+            occupancy: PieceBitboards::new(),
+            hash: 0,
+            pawn_hash: 0,
+        };
+        for piece in Piece::all() {
+            for sq in bbs[piece as usize] {
+                state.add_piece(piece, sq);
+            }
+        }
+        state.hash = zobrist::hash(&state);
+        state
+    }
+
+    /// Check that the redundant views of the board agree with each other and that the position is
+    /// sane for standard chess. Returns a description of the first problem found, if any.
+    ///
+    /// Checks everything `validate_board` does, and that each side has exactly one king.
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_board()?;
+        for color in Color::all() {
+            let kings = self.pieces(color, PieceType::King).popcount();
+            if kings != 1 {
+                return Err(format!("Side {} has {} kings.", color, kings));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the castle rights and ep square make sense for the board: each castle right has
+    /// its king and rook on their home squares, and the ep square is empty and behind a pawn which
+    /// could just have made a double push. Moves can't be applied safely to a position set up
+    /// from outside the program until this holds; `make` keeps it true from then on.
+    pub fn validate_rights(&self) -> Result<(), String> {
+        for (right, right_char) in ['K', 'Q', 'k', 'q'].into_iter().enumerate() {
+            if !self.castlerights.has(right) { continue }
+            let color = if right < 2 { Color::White } else { Color::Black };
+            let king_sq = self.castle_files.king_square(color);
+            let rook_sq = self.castle_files.rook_square(right);
+            if self.occupying_piece(king_sq) != Some(Piece::new(color, PieceType::King))
+                || self.occupying_piece(rook_sq) != Some(Piece::new(color, PieceType::Rook))
+            {
+                return Err(format!(
+                    "Castle right {} needs a king on {} and a rook on {}.",
+                    right_char, king_sq, rook_sq,
+                ));
+            }
+        }
+
+        if let Some(ep_square) = self.ep_square {
+            let color = self.side_to_move;
+            let pushed = || self.occupying_piece(ep_capture_square(ep_square, color));
+            if ep_square.rank() != Rank::R6.relative(color)
+                || self.occupying_piece(ep_square).is_some()
+                || pushed() != Some(Piece::new(!color, PieceType::Pawn))
+            {
+                return Err(format!("Invalid ep square {}.", ep_square));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the redundant views of the board agree with each other, without assuming
+    /// anything about which pieces are on the board. Variants may have any number of kings.
+    ///
+    /// Checks that:
+    /// - no two piece bitboards share a square,
+    /// - `side_bbs` is the union of each side's piece bitboards,
+    /// - the square-to-piece map matches the piece bitboards on every square,
+    /// - every square marked as holding a promoted piece holds a piece,
+    /// - the incrementally updated hashes match the hashes of the position and of its pawns.
+    pub fn validate_board(&self) -> Result<(), String> {
+        let mut seen = Bitboard::EMPTY;
+        for piece in Piece::all() {
+            let overlap = seen & self.bbs[piece as usize];
+            if let Some(sq) = overlap.lsb() {
+                return Err(format!("Square {} is set on more than one piece bitboard.", sq));
+            }
+            seen |= self.bbs[piece as usize];
+        }
+
+        for color in Color::all() {
+            let side = PieceType::all()
+                .fold(Bitboard::EMPTY, |side, kind| side | self.pieces(color, kind));
+            if side != self.side_bbs[color.idx()] {
+                return Err(format!("Side bitboard for {} does not match its pieces.", color));
+            }
+        }
+
+        for sq in Square::all() {
+            let on_bbs = Piece::all().find(|&piece| self.bbs[piece as usize].contains(sq));
+            if on_bbs != self.occupancy.get(sq) {
+                return Err(format!("Piece map does not match bitboards on {}.", sq));
+            }
+        }
+
+        if let Some(sq) = (self.promoted & !seen).lsb() {
+            return Err(format!("Empty square {} is marked as promoted.", sq));
         }
+
+        if self.hash != zobrist::hash(self) {
+            return Err("Hash does not match the position.".to_string());
+        }
+        if self.pawn_hash != zobrist::pawn_hash(self) {
+            return Err("Pawn hash does not match the pawns.".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Get the Zobrist hash of this position, which is kept equal to `zobrist::hash` by `make` and
+    /// `unmake`. This is not the Polyglot key; use `polyglot::key` for book lookups.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Get the hash of the pawns alone, which is kept equal to `zobrist::pawn_hash` by `make` and
+    /// `unmake`.
+    pub fn pawn_hash(&self) -> u64 {
+        self.pawn_hash
+    }
+
+    /// Set the castle rights, keeping the hash up to date.
+    pub fn set_castlerights(&mut self, castlerights: CastleRights) {
+        self.hash ^= zobrist::castle_rights_key(self.castlerights)
+            ^ zobrist::castle_rights_key(castlerights);
+        self.castlerights = castlerights;
+    }
+
+    /// Get the home files of each side's king and castling rooks.
+    pub fn castle_files(&self) -> CastleFiles {
+        self.castle_files
+    }
+
+    /// Set the home files of each side's king and castling rooks, and rebuild the per-square castle
+    /// rights masks used by `make`.
+    pub fn set_castle_files(&mut self, castle_files: CastleFiles) {
+        self.castle_files = castle_files;
+        self.castle_masks = castle_files.rights_masks();
+    }
+
+    /// Get the bitboard of the given color's pieces of the given type.
+    pub fn pieces(&self, color: Color, kind: PieceType) -> Bitboard {
+        self.bbs[Piece::new(color, kind) as usize]
+    }
+
+    /// Get the bitboard of both colors' pieces of the given type.
+    pub fn pieces_of_kind(&self, kind: PieceType) -> Bitboard {
+        self.pieces(Color::White, kind) | self.pieces(Color::Black, kind)
+    }
+
+    /// Get the bitboard of all of the given color's pieces.
+    pub fn side_pieces(&self, color: Color) -> Bitboard {
+        self.side_bbs[color.idx()]
+    }
+
+    /// Get the bitboard of all pieces on the board.
+    pub fn all_pieces(&self) -> Bitboard {
+        self.side_bbs[0] | self.side_bbs[1]
     }
 
     /// Find the bitboard index of the piece occupying the square given by sq_idx. If no such
@@ -196,27 +594,22 @@ impl GameState {
     /// Set the bit at the given sq_idx on the given bitboard.
     pub fn add_piece(&mut self, piece: Piece, sq: Square) {
         self.occupancy.put(sq, piece);
-        if piece as u8 <= 5 { 
-            self.side_bbs[0] |= masks::SQUARES[sq as usize];
-        } else {
-            self.side_bbs[1] |= masks::SQUARES[sq as usize];
-        }
-        self.bbs[piece as usize] |= masks::SQUARES[sq as usize]
+        self.side_bbs[piece.color().idx()] |= Bitboard::from_square(sq);
+        self.bbs[piece as usize] |= Bitboard::from_square(sq);
+        self.update_hashes(piece, sq);
     }
 
     /// Remove the piece at the given square, if one exists. Returns piece which was removed if
-    /// there was a piece on the given square, None o.w.
+    /// there was a piece on the given square, None o.w. The square is no longer marked promoted.
     pub fn remove_piece(&mut self, sq: Square) -> Option<Piece> {
         match self.occupancy.get(sq) {
             None => None,
             Some(piece) => {
                 let res = self.occupancy.remove(sq);
-                self.bbs[piece as usize] &= !masks::SQUARES[sq as usize];
-                if piece as u8 <= 5 {
-                    self.side_bbs[0] &= !masks::SQUARES[sq as usize];
-                } else {
-                    self.side_bbs[1] &= !masks::SQUARES[sq as usize];
-                }
+                self.bbs[piece as usize] &= !Bitboard::from_square(sq);
+                self.side_bbs[piece.color().idx()] &= !Bitboard::from_square(sq);
+                self.promoted &= !Bitboard::from_square(sq);
+                self.update_hashes(piece, sq);
                 res
             }
         }
@@ -228,59 +621,75 @@ impl GameState {
         self.add_piece(new_piece, sq);
     }
 
-    /// Apply the given move to this GameState, and return the GameState after the move is applied.
-    // TODO: Could do this in-place by instead not keeping position in the StateStack.
-    pub fn make(&self, game_move: GameMove) -> Self {
-        let mut new_state = self.clone();
+    /// Apply the given move to a copy of this GameState, and return the copy. This is the
+    /// copy-make counterpart of `make`.
+    pub fn make_copy(&self, game_move: GameMove) -> Self {
+        let mut new_state = *self;
+        new_state.make(game_move);
+        new_state
+    }
+
+    /// Apply the given move to this GameState in-place. Returns an UndoRecord which can be passed
+    /// to `unmake` to revert the move.
+    pub fn make(&mut self, game_move: GameMove) -> UndoRecord {
+        let mut undo = UndoRecord {
+            game_move,
+            captured: None,
+            captured_promoted: false,
+            castlerights: self.castlerights,
+            ep_square: self.ep_square,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_clock: self.fullmove_clock,
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
+        };
+        let color = self.side_to_move;
         let mut reset_halfmove_clock = false;
 
         let move_type = game_move.move_type();
-        let castle_color_flag = if self.white_to_move { 0 } else { 2 };
+
+        self.set_ep_square(None);
+
+        // Handles drops from the pocket.
+        if move_type == MoveType::Drop {
+            let kind = game_move.drop_kind().expect("Invalid move.");
+            let pocket = &mut self.pockets[color.idx()][kind as usize];
+            *pocket = pocket.checked_sub(1).expect("Illegal move; piece not in pocket");
+            self.add_piece(Piece::new(color, kind), game_move.tosquare());
+
+            // Update side to move, clocks.
+            self.set_side_to_move(!color);
+            if color == Color::Black { self.fullmove_clock += 1 }
+            self.halfmove_clock += 1;
+
+            debug_assert_eq!(self.validate_board(), Ok(()));
+            return undo;
+        }
 
         // Handles castling.
         if (move_type == MoveType::QueenCastle) || (move_type == MoveType::KingCastle) {
-            // Move the king.
-            let (king_from_sq, king) = 
-                if self.white_to_move { (4, Piece::WhiteKing) } else { (60, Piece::BlackKing) };
-            let king_to_sq = match (move_type, self.white_to_move) {
-                (MoveType::QueenCastle, true)   => 2,
-                (MoveType::KingCastle, true)    => 6,
-                (MoveType::QueenCastle, false)  => 58,
-                (MoveType::KingCastle, false)   => 62,
-                _ => 100,
-            };
-            new_state.remove_piece(king_from_sq);
-            new_state.add_piece(king, king_to_sq);
-
-            // Move the rook.
-            let rook = if self.white_to_move { Piece::WhiteRook } else { Piece::BlackRook };
-            let rook_from_sq = match (move_type, self.white_to_move) {
-                (MoveType::QueenCastle, true)   => 0,
-                (MoveType::KingCastle, true)    => 7,
-                (MoveType::QueenCastle, false)  => 56,
-                (MoveType::KingCastle, false)   => 63,
-                _ => 100,
-            };
-            let rook_to_sq = match (move_type, self.white_to_move) {
-                (MoveType::QueenCastle, true)   => 3,
-                (MoveType::KingCastle, true)    => 5,
-                (MoveType::QueenCastle, false)  => 59,
-                (MoveType::KingCastle, false)   => 61,
-                _ => 100,
-            };
-            new_state.remove_piece(rook_from_sq);
-            new_state.add_piece(rook, rook_to_sq);
+            let (king_from_sq, king_to_sq, rook_from_sq, rook_to_sq) =
+                self.castle_squares(move_type, color);
+            let king = Piece::new(color, PieceType::King);
+            let rook = Piece::new(color, PieceType::Rook);
+
+            // Lift both pieces before placing either; in Chess960 the king or rook may land on
+            // the other's starting square.
+            self.remove_piece(king_from_sq);
+            self.remove_piece(rook_from_sq);
+            self.add_piece(king, king_to_sq);
+            self.add_piece(rook, rook_to_sq);
 
             // Clear castle rights.
-            new_state.castlerights[castle_color_flag+0] = false;
-            new_state.castlerights[castle_color_flag+1] = false;
+            self.update_castlerights(king_from_sq, rook_from_sq);
 
-            // Update ep square, side to move, clocks.
-            new_state.white_to_move = !self.white_to_move;
-            if !self.white_to_move { new_state.fullmove_clock += 1 }
-            new_state.halfmove_clock = self.halfmove_clock + 1;
+            // Update side to move, clocks.
+            self.set_side_to_move(!color);
+            if color == Color::Black { self.fullmove_clock += 1 }
+            self.halfmove_clock += 1;
 
-            return new_state;
+            debug_assert_eq!(self.validate_board(), Ok(()));
+            return undo;
         }
 
         let fromsquare = game_move.fromsquare();
@@ -291,76 +700,233 @@ impl GameState {
         // If capture, find the capturing square (either tosquare or e.p. square), and remove the
         // existing piece there.
         if game_move.is_capture() {
-            let mut cap_sq = tosquare;
-            if move_type == MoveType::EpCapture {
-                cap_sq = match self.white_to_move {
-                    true    => self.ep_square.unwrap() - 8,
-                    false   => self.ep_square.unwrap() + 8,
-                }
-            }
-            new_state.remove_piece(cap_sq);
+            let cap_sq = if move_type == MoveType::EpCapture {
+                ep_capture_square(undo.ep_square.unwrap(), color)
+            } else {
+                tosquare
+            };
+            undo.captured_promoted = self.promoted.contains(cap_sq);
+            undo.captured = self.remove_piece(cap_sq);
             reset_halfmove_clock = true;
         }
 
-        // Move the actual piece.
-        new_state.remove_piece(fromsquare);
-        new_state.add_piece(moving, tosquare);
+        // Move the actual piece, along with its promoted marker.
+        let moving_promoted = self.promoted.contains(fromsquare);
+        self.remove_piece(fromsquare);
+        self.add_piece(moving, tosquare);
 
         // Reset halfmove clock if pawn was moved.
         reset_halfmove_clock =
-            reset_halfmove_clock || (moving == Piece::WhitePawn || moving == Piece::BlackPawn);
+            reset_halfmove_clock || moving.kind() == PieceType::Pawn;
 
         // Promote the moved piece, if necessary.
         if game_move.is_promo() {
-            let promo_piece = game_move.promo_piece(self.white_to_move)
+            let promo_piece = game_move.promo_piece(color)
                 .expect("Invalid move.");
-            new_state.promote_piece(tosquare, promo_piece);
+            self.promote_piece(tosquare, promo_piece);
+        }
+        if moving_promoted || game_move.is_promo() {
+            self.promoted |= Bitboard::from_square(tosquare);
+        }
+
+        // Update side to move, ep square, clocks.
+        self.set_side_to_move(!color);
+        if move_type == MoveType::DoublePawnPush {
+            self.set_ep_square(tosquare.backward(color));
         }
+        if color == Color::Black { self.fullmove_clock += 1 }
+        self.halfmove_clock = if reset_halfmove_clock { 0 } else { self.halfmove_clock + 1 };
+
+        // Update castle rights if necessary.
+        self.update_castlerights(fromsquare, tosquare);
 
-        // Update ep square, side to move, clocks.
-        new_state.ep_square = if move_type != MoveType::DoublePawnPush { None } else {
-            if self.white_to_move { Some(tosquare - 8) } else { Some(tosquare + 8) }
+        debug_assert_eq!(self.validate_board(), Ok(()));
+        undo
+    }
+
+    /// Clear the castle rights lost by a move from fromsquare to tosquare; i.e., any rights whose
+    /// king or rook home square is touched by the move.
+    pub(crate) fn update_castlerights(&mut self, fromsquare: Square, tosquare: Square) {
+        self.set_castlerights(
+            self.castlerights
+                & self.castle_masks[fromsquare.idx()]
+                & self.castle_masks[tosquare.idx()],
+        );
+    }
+
+    /// Toggle the given piece on the given square in the hash, and in the pawn hash for a pawn.
+    fn update_hashes(&mut self, piece: Piece, sq: Square) {
+        let key = zobrist::piece_key(piece, sq);
+        self.hash ^= key;
+        if piece.kind() == PieceType::Pawn { self.pawn_hash ^= key }
+    }
+
+    /// Set the side to move, keeping the hash up to date.
+    fn set_side_to_move(&mut self, color: Color) {
+        if color != self.side_to_move { self.hash ^= zobrist::turn_key() }
+        self.side_to_move = color;
+    }
+
+    /// Set the ep square, keeping the hash up to date.
+    pub(crate) fn set_ep_square(&mut self, ep_square: Option<Square>) {
+        self.hash ^= self.ep_square.map_or(0, |sq| zobrist::ep_key(sq.file()))
+            ^ ep_square.map_or(0, |sq| zobrist::ep_key(sq.file()));
+        self.ep_square = ep_square;
+    }
+
+    /// Get the (king from, king to, rook from, rook to) squares for the given castle move type and
+    /// side. The king and rook always end on the g- and f-files (kingside) or c- and d-files
+    /// (queenside), wherever they start.
+    pub(crate) fn castle_squares(
+        &self,
+        move_type: MoveType,
+        color: Color,
+    ) -> (Square, Square, Square, Square) {
+        let right = castle_right_idx(move_type, color);
+        let back_rank = Rank::R1.relative(color);
+        let (king_to_file, rook_to_file) = match move_type {
+            MoveType::KingCastle => (File::G, File::F),
+            _ => (File::C, File::D),
         };
+        (
+            self.castle_files.king_square(color),
+            Square::from_file_rank(king_to_file, back_rank),
+            self.castle_files.rook_square(right),
+            Square::from_file_rank(rook_to_file, back_rank),
+        )
+    }
 
-        new_state.white_to_move = !self.white_to_move;
-        if !self.white_to_move { new_state.fullmove_clock += 1 }
-        new_state.halfmove_clock = if reset_halfmove_clock { 0 } else { self.halfmove_clock + 1 };
+    /// Revert the move recorded in the given UndoRecord, which must be the last move applied to
+    /// this GameState with `make`.
+    pub fn unmake(&mut self, undo: UndoRecord) {
+        let game_move = undo.game_move;
+        let move_type = game_move.move_type();
+        let color = !self.side_to_move;
 
-        // Update castle rights if necessary.
-        let has_castlerights = 
-            self.castlerights[castle_color_flag+1] || self.castlerights[castle_color_flag+1];
-        if has_castlerights && (moving == Piece::WhiteRook || moving == Piece::BlackRook) {
-            match (moving, fromsquare) {
-                (Piece::WhiteRook,  0) => new_state.castlerights[0] = false,
-                (Piece::WhiteRook,  7) => new_state.castlerights[1] = false,
-                (Piece::BlackRook, 63) => new_state.castlerights[3] = false,
-                (Piece::BlackRook, 56) => new_state.castlerights[3] = false,
-                _ => ()
+        // The hashes are restored from the record at the end, so these needn't update them.
+        self.side_to_move = color;
+        self.castlerights = undo.castlerights;
+        self.ep_square = undo.ep_square;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_clock = undo.fullmove_clock;
+
+        if move_type == MoveType::Drop {
+            // Undo drops by taking the piece back off the board and into the pocket.
+            let dropped =
+                self.remove_piece(game_move.tosquare()).expect("Invalid undo; no piece to undrop.");
+            self.pockets[color.idx()][dropped.kind() as usize] += 1;
+        } else if (move_type == MoveType::QueenCastle) || (move_type == MoveType::KingCastle) {
+            // Undo castling by moving the king and rook back.
+            let (king_from_sq, king_to_sq, rook_from_sq, rook_to_sq) =
+                self.castle_squares(move_type, color);
+            let king = Piece::new(color, PieceType::King);
+            let rook = Piece::new(color, PieceType::Rook);
+            self.remove_piece(king_to_sq).expect("Invalid undo; no king to unmake.");
+            self.remove_piece(rook_to_sq).expect("Invalid undo; no rook to unmake.");
+            self.add_piece(king, king_from_sq);
+            self.add_piece(rook, rook_from_sq);
+        } else {
+            let fromsquare = game_move.fromsquare();
+            let tosquare = game_move.tosquare();
+
+            // Move the piece back, demoting it to a pawn if this move promoted it, or else taking
+            // its promoted marker with it.
+            let was_promoted = self.promoted.contains(tosquare) && !game_move.is_promo();
+            let moved = self.remove_piece(tosquare).expect("Invalid undo; no piece on tosquare.");
+            let moved =
+                if game_move.is_promo() { Piece::new(color, PieceType::Pawn) } else { moved };
+            self.add_piece(moved, fromsquare);
+            if was_promoted { self.promoted |= Bitboard::from_square(fromsquare) }
+
+            // Restore the captured piece.
+            if let Some(captured) = undo.captured {
+                let cap_sq = if move_type == MoveType::EpCapture {
+                    ep_capture_square(undo.ep_square.unwrap(), color)
+                } else {
+                    tosquare
+                };
+                self.add_piece(captured, cap_sq);
+                if undo.captured_promoted { self.promoted |= Bitboard::from_square(cap_sq) }
             }
         }
 
-        new_state
+        self.hash = undo.hash;
+        self.pawn_hash = undo.pawn_hash;
+        debug_assert_eq!(self.validate_board(), Ok(()));
+    }
+}
+
+
+impl GameState {
+    /// Draw the board as an 8x8 grid of Unicode piece symbols with rank and file labels, from
+    /// white's point of view, or black's if flipped.
+    pub fn diagram(&self, flipped: bool) -> String {
+        let mut ranks: Vec<Rank> = Rank::all().rev().collect();
+        let mut files: Vec<File> = File::all().collect();
+        if flipped {
+            ranks.reverse();
+            files.reverse();
+        }
+
+        let mut result = String::new();
+        for &rank in &ranks {
+            result.push_str(&rank.to_string());
+            for &file in &files {
+                let sq = Square::from_file_rank(file, rank);
+                result.push(' ');
+                result.push(self.occupying_piece(sq).map_or('.', Piece::symbol));
+            }
+            result.push('\n');
+        }
+        result.push(' ');
+        for &file in &files {
+            result.push_str(&format!(" {}", file));
+        }
+        result.push('\n');
+        result
+    }
+}
+
+impl fmt::Display for GameState {
+    /// Draw the board from white's point of view. See `diagram`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.diagram(false))
     }
 }
 
 
+/// Get the castlerights index of the right used by the given castle move type and side.
+pub(crate) fn castle_right_idx(move_type: MoveType, color: Color) -> usize {
+    match move_type {
+        MoveType::KingCastle => 2 * color.idx(),
+        MoveType::QueenCastle => 2 * color.idx() + 1,
+        _ => panic!("Not a castle move."),
+    }
+}
+
+/// Get the square of the pawn captured by an e.p. capture onto the given ep square.
+fn ep_capture_square(ep_square: Square, color: Color) -> Square {
+    ep_square.backward(color).expect("Invalid e.p. square.")
+}
+
+
 /// Data structure to map from square number -> occupying piece.
 #[derive(Copy, Clone)]
-struct PieceBitBoards {
+struct PieceBitboards {
     map: [Piece; 64],
 }
 
 /// Map from squre to occupying piece.
-impl PieceBitBoards {
+impl PieceBitboards {
     pub fn new() -> Self {
-        PieceBitBoards {
+        PieceBitboards {
             map: [Piece::Null; 64],
         }
     }
 
     /// Get Some(<piece_at_sq>) or None if there is no such piece.
     pub fn get(&self, sq: Square) -> Option<Piece> {
-        match self.map[sq as usize] {
+        match self.map[sq.idx()] {
             Piece::Null => None,
             pi => Some(pi),
         }
@@ -370,58 +936,182 @@ impl PieceBitBoards {
     /// piece, returns None.
     pub fn remove(&mut self, sq: Square) -> Option<Piece> {
         let removed = self.get(sq);
-        self.map[sq as usize] = Piece::Null;
+        self.map[sq.idx()] = Piece::Null;
         removed
     }
 
     /// Put the piece at the given square in the map.
     pub fn put(&mut self, sq: Square, pi: Piece) {
-        self.map[sq as usize] = pi;
+        self.map[sq.idx()] = pi;
     }
 }
 
 
-/// We store the state stack in the stack for fast access. Thus we need a max size.
-pub const MAX_MOVESTACK_DEPTH: usize = 100;
+/// The most recent UndoRecords are stored in the stack for fast access during search, which needs
+/// a max size. Older UndoRecords spill over onto the heap, so games can be arbitrarily long.
+pub const FAST_UNDO_DEPTH: usize = 128;
 
-/// Represents a stack of game states that have occured from the initial position.
-struct StateStack {
-    backing: [Option<GameState>; MAX_MOVESTACK_DEPTH],
-    size: usize,
+/// Represents a stack of UndoRecords for the moves made from the initial position.
+///
+/// The top FAST_UNDO_DEPTH records live in a fixed-size ring buffer; pushing onto a full ring
+/// buffer moves its oldest record into the `older` Vec.
+struct UndoStack {
+    recent: [Option<(UndoRecord, VariantUndo)>; FAST_UNDO_DEPTH],
+    recent_start: usize,
+    recent_size: usize,
+    older: Vec<(UndoRecord, VariantUndo)>,
 }
 
-impl StateStack {
-    /// Create a new empty StateStack.
+impl UndoStack {
+    /// Create a new empty UndoStack.
     pub fn new() -> Self {
-        StateStack {
-            backing: [None; MAX_MOVESTACK_DEPTH],
-            size: 0,
+        UndoStack {
+            recent: [None; FAST_UNDO_DEPTH],
+            recent_start: 0,
+            recent_size: 0,
+            older: Vec::new(),
         }
     }
 
-    /// Add a GameState to the top of the StateStack.
-    pub fn push(&mut self, elt: GameState) {
-        self.backing[self.size] = Some(elt);
-        self.size += 1;
+    /// Add an UndoRecord and its variant undo data to the top of the UndoStack.
+    pub fn push(&mut self, elt: (UndoRecord, VariantUndo)) {
+        if self.recent_size == FAST_UNDO_DEPTH {
+            let oldest = self.recent[self.recent_start].take().unwrap();
+            self.older.push(oldest);
+            self.recent_start = (self.recent_start + 1) % FAST_UNDO_DEPTH;
+            self.recent_size -= 1;
+        }
+        self.recent[(self.recent_start + self.recent_size) % FAST_UNDO_DEPTH] = Some(elt);
+        self.recent_size += 1;
     }
 
-    /// Get an immutable reference to the top element on the StateStack, or None if the stack is
-    /// empty.
-    pub fn peek(&self) -> Option<&GameState> {
-        if self.size <= 0 {
-            return None;
+    /// Remove and return the top UndoRecord on the UndoStack and its variant undo data, or None if
+    /// the stack is empty.
+    pub fn pop(&mut self) -> Option<(UndoRecord, VariantUndo)> {
+        if self.recent_size == 0 {
+            return self.older.pop();
         }
-        self.backing[self.size-1].as_ref()
+        self.recent_size -= 1;
+        self.recent[(self.recent_start + self.recent_size) % FAST_UNDO_DEPTH].take()
     }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+    use crate::bits::masks;
 
-    /// Remove and return the top GameState on the StateStack, or None if the stack is empty
-    pub fn pop(&mut self) -> Option<GameState> {
-        if self.size <= 0 {
-            return None;
+    #[test]
+    fn test_piece_decomposition() {
+        for piece in Piece::all() {
+            assert_eq!(Piece::new(piece.color(), piece.kind()), piece);
         }
-        let mut res = None;
-        std::mem::swap(&mut self.backing[self.size], &mut res);
-        self.size -= 1;
-        res
+        assert_eq!(Piece::all().count(), 12);
+        assert_eq!(Piece::new(Color::Black, PieceType::Knight), Piece::BlackKnight);
+        assert_eq!(Piece::WhiteQueen.color(), Color::White);
+        assert_eq!(Piece::BlackBishop.kind(), PieceType::Bishop);
+    }
+
+    #[test]
+    fn test_piece_queries() {
+        let state = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let white_rooks = Bitboard::from_square(Square::A1) | Bitboard::from_square(Square::H1);
+        assert_eq!(state.pieces(Color::White, PieceType::Rook), white_rooks);
+        assert_eq!(state.pieces(Color::Black, PieceType::King), Bitboard::from_square(Square::E8));
+        assert_eq!(state.pieces_of_kind(PieceType::Pawn), masks::RANK_2 | masks::RANK_7);
+        assert_eq!(state.side_pieces(Color::White), masks::RANK_1 | masks::RANK_2);
+        let all = masks::RANK_1 | masks::RANK_2 | masks::RANK_7 | masks::RANK_8;
+        assert_eq!(state.all_pieces(), all);
+    }
+
+    #[test]
+    fn test_new_derives_views() {
+        let parsed = parse_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        let rights = CastleRights::ALL;
+        let state = GameState::new(parsed.bbs, Color::White, Some(Square::D6), 0, 1, rights);
+        assert_eq!(state.validate(), Ok(()));
+        assert_eq!(state.side_bbs, parsed.side_bbs);
+        for sq in Square::all() {
+            assert_eq!(state.occupying_piece(sq), parsed.occupying_piece(sq));
+        }
+    }
+
+    #[test]
+    fn test_chess960_castle_rights_masks() {
+        let mut state = parse_fen("1r1k1r1r/8/8/8/8/8/8/RK4R1 w KQfq - 0 1");
+        state.update_castlerights(Square::G1, Square::G2);
+        assert_eq!(state.castlerights, CastleRights(0b1110));
+        state.update_castlerights(Square::H8, Square::H7);
+        assert_eq!(state.castlerights, CastleRights(0b1110));
+        state.update_castlerights(Square::A1, Square::B8);
+        assert_eq!(state.castlerights, CastleRights(0b0100));
+        state.update_castlerights(Square::D8, Square::D7);
+        assert_eq!(state.castlerights, CastleRights::NONE);
+    }
+
+    #[test]
+    fn test_validate_catches_inconsistencies() {
+        let start = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(start.validate(), Ok(()));
+
+        let mut overlapping = start;
+        overlapping.bbs[Piece::WhiteQueen as usize] |= Bitboard::from_square(Square::E1);
+        assert!(overlapping.validate().is_err());
+
+        let mut stale_side = start;
+        stale_side.side_bbs[Color::Black.idx()] = Bitboard::EMPTY;
+        assert!(stale_side.validate().is_err());
+
+        let mut stale_map = start;
+        stale_map.bbs[Piece::WhitePawn as usize] &= !Bitboard::from_square(Square::E2);
+        stale_map.side_bbs[Color::White.idx()] &= !Bitboard::from_square(Square::E2);
+        assert!(stale_map.validate().is_err());
+
+        let mut no_king = start;
+        no_king.remove_piece(Square::E8);
+        assert!(no_king.validate().is_err());
+
+        let mut stale_hash = start;
+        stale_hash.castlerights = CastleRights::NONE;
+        assert!(stale_hash.validate().is_err());
+    }
+
+    #[test]
+    fn test_hash_follows_make_and_unmake() {
+        let start = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let mut state = start;
+        let moves = [
+            (Square::E2, Square::E4, MoveType::DoublePawnPush),
+            (Square::D7, Square::D5, MoveType::DoublePawnPush),
+            (Square::E4, Square::E5, MoveType::Quiet),
+            (Square::F7, Square::F5, MoveType::DoublePawnPush),
+            (Square::E5, Square::F6, MoveType::EpCapture),
+        ];
+        let mut undos = Vec::new();
+        for (fromsquare, tosquare, move_type) in moves {
+            undos.push(state.make(GameMove::new(fromsquare, tosquare, move_type)));
+            assert_eq!(state.hash(), zobrist::hash(&state));
+            assert_eq!(state.pawn_hash(), zobrist::pawn_hash(&state));
+        }
+
+        while let Some(undo) = undos.pop() {
+            state.unmake(undo);
+            assert_eq!(state.validate(), Ok(()));
+        }
+        assert_eq!(state.hash(), start.hash());
+        assert_eq!(state.pawn_hash(), start.pawn_hash());
+    }
+
+    #[test]
+    fn test_hash_is_not_the_polyglot_key() {
+        // No black pawn can capture on e3, so the Polyglot key leaves out the ep square but the
+        // hash doesn't.
+        let with_ep = parse_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+        let without_ep = parse_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+        assert_eq!(crate::polyglot::key(&with_ep), crate::polyglot::key(&without_ep));
+        assert_ne!(with_ep.hash(), without_ep.hash());
+        assert_ne!(with_ep.hash(), crate::polyglot::key(&with_ep));
     }
 }
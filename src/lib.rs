@@ -4,3 +4,13 @@ pub mod move_gen;
 pub mod bits;
 pub mod fen;
 pub mod move_list;
+pub mod san;
+pub mod pgn;
+pub mod polyglot;
+pub mod zobrist;
+pub mod square;
+pub mod chess960;
+pub mod variant;
+pub mod console;
+pub mod sprt;
+pub mod eval;
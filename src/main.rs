@@ -1,7 +1,23 @@
-use bongcloud::game_state::bb_utils::debug_print;
-use bongcloud::bits::masks::KNIGHT_MOVES;
+use std::io::{ self, BufRead, Write };
+use bongcloud::console::Console;
 
 pub fn main() {
-    let bb = KNIGHT_MOVES[29];
-    debug_print(bb);
+    let mut console = Console::new();
+    println!("{}", console.board());
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().expect("Could not flush stdout.");
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).expect("Could not read stdin.") == 0 { break }
+        if matches!(line.trim(), "quit" | "exit") { break }
+
+        match console.handle(&line) {
+            Ok(output) if output.is_empty() => {},
+            Ok(output) => println!("{}", output),
+            Err(err) => println!("{}", err),
+        }
+    }
 }
@@ -1,37 +1,170 @@
-///! This file contains functions for generating legal moves (stored in a MoveList) from a given 
-///! GameState.
-use crate::game_state::{ GameState, BitBoard, Square };
-use crate::bits::biterator::{ biterator, bitboarderator };
+//! This file contains functions for generating legal moves (stored in a MoveList) from a given 
+//! GameState.
+use crate::game_state::{ self, GameState, Color, PieceType };
+use crate::bits::bitboard::{ Bitboard, Direction };
 use crate::bits::masks;
 use crate::game_move::{ GameMove, MoveType };
 use crate::move_list::MoveList;
+use crate::square::{ Square, Rank };
 
 
-pub fn gen_legal_moves(state: GameState) -> MoveList {
+pub fn gen_legal_moves(_state: GameState) -> MoveList {
     MoveList::new()   
 }
 
+
+/// Get the squares attacked by a bishop on the given square, given the occupancy of the board. The
+/// first blocker in each direction is included.
+pub fn bishop_attacks(sq: Square, occupancy: Bitboard) -> Bitboard {
+    [Direction::NorthEast, Direction::NorthWest, Direction::SouthEast, Direction::SouthWest]
+        .into_iter()
+        .fold(Bitboard::EMPTY, |attacks, dir| attacks | ray_attacks(sq, occupancy, dir))
+}
+
+/// Get the squares attacked by a rook on the given square, given the occupancy of the board. The
+/// first blocker in each direction is included.
+pub fn rook_attacks(sq: Square, occupancy: Bitboard) -> Bitboard {
+    [Direction::North, Direction::South, Direction::East, Direction::West]
+        .into_iter()
+        .fold(Bitboard::EMPTY, |attacks, dir| attacks | ray_attacks(sq, occupancy, dir))
+}
+
+/// Get the squares attacked by a queen on the given square, given the occupancy of the board.
+pub fn queen_attacks(sq: Square, occupancy: Bitboard) -> Bitboard {
+    bishop_attacks(sq, occupancy) | rook_attacks(sq, occupancy)
+}
+
+/// Returns whether any of the given side's pieces attack the given square.
+pub fn is_square_attacked(state: &GameState, sq: Square, by: Color) -> bool {
+    let occupancy = state.all_pieces();
+    let diagonal = state.pieces(by, PieceType::Bishop) | state.pieces(by, PieceType::Queen);
+    let straight = state.pieces(by, PieceType::Rook) | state.pieces(by, PieceType::Queen);
+
+    // A pawn of `by` attacks sq iff a pawn of the other side on sq would attack the pawn.
+    !(masks::PAWN_ATTACKS[(!by).idx()][sq.idx()] & state.pieces(by, PieceType::Pawn)).is_empty()
+        || !(masks::KNIGHT_MOVES[sq.idx()] & state.pieces(by, PieceType::Knight)).is_empty()
+        || !(masks::KING_MOVES[sq.idx()] & state.pieces(by, PieceType::King)).is_empty()
+        || !(bishop_attacks(sq, occupancy) & diagonal).is_empty()
+        || !(rook_attacks(sq, occupancy) & straight).is_empty()
+}
+
+/// Returns whether the given side's king is attacked. A side with no king is never in check.
+pub fn in_check(state: &GameState, color: Color) -> bool {
+    state.pieces(color, PieceType::King)
+        .into_iter()
+        .any(|king_sq| is_square_attacked(state, king_sq, !color))
+}
+
+/// Returns whether the side to move could make the given move: its own piece is on the
+/// fromsquare, the piece can reach the tosquare on the current board, and the move type matches
+/// what is on the tosquare. Drops need the piece in the mover's pocket, and castles must be
+/// generated by `append_castles`, so they can't cross attacked squares.
+///
+/// Doesn't check whether the move leaves the mover's king in check, or whether the variant being
+/// played has drops.
+pub fn is_pseudo_legal(state: &GameState, game_move: GameMove) -> bool {
+    let color = state.side_to_move;
+    let fromsquare = game_move.fromsquare();
+    let tosquare = game_move.tosquare();
+    let to_bb = Bitboard::from_square(tosquare);
+    let move_type = game_move.move_type();
+    match move_type {
+        MoveType::NullMove => return false,
+        MoveType::Drop => {
+            let Some(kind) = game_move.drop_kind() else { return false };
+            let back_rank = !(to_bb & (masks::RANK_1 | masks::RANK_8)).is_empty();
+            return kind != PieceType::King
+                && state.pockets[color.idx()][kind as usize] > 0
+                && state.occupying_piece(tosquare).is_none()
+                && !(kind == PieceType::Pawn && back_rank);
+        },
+        MoveType::KingCastle | MoveType::QueenCastle => {
+            let mut castles = MoveList::new();
+            move_gen_utils::append_castles(&mut castles, state);
+            while let Some(castle) = castles.pop() {
+                if castle.data == game_move.data { return true }
+            }
+            return false;
+        },
+        _ => {},
+    }
+
+    let Some(moving) = state.occupying_piece(fromsquare).filter(|piece| piece.color() == color)
+    else {
+        return false;
+    };
+    let target_ok = match state.occupying_piece(tosquare) {
+        Some(piece) => game_move.is_capture() && piece.color() != color,
+        None => !game_move.is_capture() || move_type == MoveType::EpCapture,
+    };
+    if !target_ok { return false }
+
+    let occupancy = state.all_pieces();
+    let reach = match moving.kind() {
+        PieceType::Pawn => {
+            if game_move.is_promo() != (tosquare.rank() == Rank::R8.relative(color)) {
+                return false;
+            }
+            let push = fromsquare.forward(color);
+            return match move_type {
+                MoveType::EpCapture => state.ep_square == Some(tosquare)
+                    && !(masks::PAWN_ATTACKS[color.idx()][fromsquare.idx()] & to_bb).is_empty(),
+                MoveType::DoublePawnPush => fromsquare.rank() == Rank::R2.relative(color)
+                    && push.is_some_and(|sq| state.occupying_piece(sq).is_none())
+                    && push.and_then(|sq| sq.forward(color)) == Some(tosquare),
+                _ if game_move.is_capture() =>
+                    !(masks::PAWN_ATTACKS[color.idx()][fromsquare.idx()] & to_bb).is_empty(),
+                _ => push == Some(tosquare),
+            };
+        },
+        PieceType::Knight => masks::KNIGHT_MOVES[fromsquare.idx()],
+        PieceType::Bishop => bishop_attacks(fromsquare, occupancy),
+        PieceType::Rook => rook_attacks(fromsquare, occupancy),
+        PieceType::Queen => queen_attacks(fromsquare, occupancy),
+        PieceType::King => masks::KING_MOVES[fromsquare.idx()],
+    };
+    matches!(move_type, MoveType::Quiet | MoveType::Capture) && !(reach & to_bb).is_empty()
+}
+
+/// Get the squares attacked along a ray from sq, up to and including the first blocker.
+fn ray_attacks(sq: Square, occupancy: Bitboard, dir: Direction) -> Bitboard {
+    let ray = masks::RAYS[dir.idx()][sq.idx()];
+    let (file_delta, rank_delta) = dir.delta();
+    // The closest blocker has the lowest index on rays going up the board, the highest otherwise.
+    let blocker = if rank_delta > 0 || (rank_delta == 0 && file_delta > 0) {
+        (ray & occupancy).lsb()
+    } else {
+        (ray & occupancy).msb()
+    };
+    match blocker {
+        Some(blocker_sq) => ray & !masks::RAYS[dir.idx()][blocker_sq.idx()],
+        None => ray,
+    }
+}
+
+// Not wired into gen_legal_moves yet.
+#[allow(dead_code)]
 mod move_gen_utils {
     use super::*;
 
     /// Append knight moves to the given MoveList.
     pub fn append_knight_moves(
         move_list: &mut MoveList,
-        movable_knights: BitBoard,
-        stm_occupancy: BitBoard,
-        opp_occupancy: BitBoard,
+        movable_knights: Bitboard,
+        stm_occupancy: Bitboard,
+        opp_occupancy: Bitboard,
     ) {
-        for knight_sq in biterator(movable_knights) {
-            let all_moves_bb = masks::KNIGHT_MOVES[knight_sq as usize];
+        for knight_sq in movable_knights {
+            let all_moves_bb = masks::KNIGHT_MOVES[knight_sq.idx()];
 
             let moves_bb = all_moves_bb & !(stm_occupancy | opp_occupancy);
-            for move_sq in biterator(moves_bb) {
-                move_list.push(GameMove::new(knight_sq as u8, move_sq as u8, MoveType::Quiet));
+            for move_sq in moves_bb {
+                move_list.push(GameMove::new(knight_sq, move_sq, MoveType::Quiet));
             }
 
             let captures_bb = all_moves_bb & opp_occupancy;
-            for cap_sq in biterator(captures_bb) {
-                move_list.push(GameMove::new(knight_sq as u8, cap_sq as u8, MoveType::Capture));
+            for cap_sq in captures_bb {
+                move_list.push(GameMove::new(knight_sq, cap_sq, MoveType::Capture));
             }
         }
     }
@@ -39,44 +172,223 @@ mod move_gen_utils {
     /// Append single pawn pushes to the given MoveList.
     pub fn append_pawn_single_pushes(
         move_list: &mut MoveList,
-        pushable_pawns: BitBoard,
-        total_occupancy: BitBoard,
-        white_to_move: bool,
+        pushable_pawns: Bitboard,
+        total_occupancy: Bitboard,
+        color: Color,
     ) {
-        let push_mask = if white_to_move { pushable_pawns << 8 } else { pushable_pawns >> 8 };
+        let push_mask = match color {
+            Color::White => pushable_pawns.north(),
+            Color::Black => pushable_pawns.south(),
+        };
         let pushes = push_mask & !total_occupancy;
-        for move_sq in biterator(pushes) {
-            let push_move = if white_to_move {
-                GameMove::new(move_sq-8, move_sq, MoveType::Quiet)
-            } else {
-                GameMove::new(move_sq+8, move_sq, MoveType::Quiet)
-            };
-            move_list.push(push_move);
+        for move_sq in pushes {
+            let from_sq = move_sq.backward(color).unwrap();
+            move_list.push(GameMove::new(from_sq, move_sq, MoveType::Quiet));
         }
     }
 
     /// Append double pawn pushes to the given MoveList.
     pub fn append_pawn_double_pushes(
         move_list: &mut MoveList,
-        pushable_pawns: BitBoard,
-        total_occupancy: BitBoard,
-        white_to_move: bool,
+        pushable_pawns: Bitboard,
+        total_occupancy: Bitboard,
+        color: Color,
     ) {
-        let started_pawns = if white_to_move { pushable_pawns & masks::RANK_2 } 
-            else { pushable_pawns & masks::RANK_7 };
-        let not_blocked = if white_to_move {
-            started_pawns & ! (total_occupancy << 8 | total_occupancy << 16)
-        } else {
-            started_pawns & ! (total_occupancy >> 8 | total_occupancy >> 16)
+        let push_mask = match color {
+            Color::White => {
+                let started_pawns = pushable_pawns & masks::RANK_2;
+                (started_pawns & !(total_occupancy.south() | total_occupancy.south().south())) << 16
+            },
+            Color::Black => {
+                let started_pawns = pushable_pawns & masks::RANK_7;
+                (started_pawns & !(total_occupancy.north() | total_occupancy.north().north())) >> 16
+            },
         };
-        let push_mask = if white_to_move { not_blocked << 16 } else { not_blocked >> 16 };
-        for move_sq in biterator(push_mask) {
-            let push_move = if white_to_move {
-                GameMove::new(move_sq-16, move_sq, MoveType::DoublePawnPush)
+        for move_sq in push_mask {
+            let from_sq = move_sq.offset(0, -2 * color.forward()).unwrap();
+            move_list.push(GameMove::new(from_sq, move_sq, MoveType::DoublePawnPush));
+        }
+    }
+
+    /// Append castling moves for the side to move to the given MoveList, as the king moving to its
+    /// destination square. Handles Chess960 placements, where the king may start on any file
+    /// between the rooks and either piece may end on the other's starting square.
+    ///
+    /// The squares the king and rook cross must be empty, and the king may not castle out of,
+    /// through or into check.
+    pub fn append_castles(move_list: &mut MoveList, state: &GameState) {
+        let color = state.side_to_move;
+        for move_type in [MoveType::KingCastle, MoveType::QueenCastle] {
+            if !state.castlerights.has(game_state::castle_right_idx(move_type, color)) { continue }
+
+            let (king_from, king_to, rook_from, rook_to) = state.castle_squares(move_type, color);
+            let king_path = masks::BETWEEN[king_from.idx()][king_to.idx()]
+                | Bitboard::from_square(king_to);
+            let rook_path = masks::BETWEEN[rook_from.idx()][rook_to.idx()]
+                | Bitboard::from_square(rook_to);
+            let castlers = Bitboard::from_square(king_from) | Bitboard::from_square(rook_from);
+            if !(state.all_pieces() & (king_path | rook_path) & !castlers).is_empty() { continue }
+
+            // Look at the king's squares with the castlers lifted, so that in Chess960 a rook
+            // behind the castling rook is seen to attack the king's destination.
+            let mut lifted = *state;
+            lifted.remove_piece(king_from);
+            lifted.remove_piece(rook_from);
+            let attacked = (king_path | Bitboard::from_square(king_from))
+                .into_iter()
+                .any(|sq| is_square_attacked(&lifted, sq, !color));
+            if !attacked {
+                move_list.push(GameMove::new(king_from, king_to, move_type));
+            }
+        }
+    }
+
+    /// Append drops of the pieces in the side to move's pocket to the given MoveList. Pieces may be
+    /// dropped on any empty square, except that pawns can't be dropped on the 1st or 8th rank.
+    pub fn append_drops(move_list: &mut MoveList, state: &GameState) {
+        let color = state.side_to_move;
+        let empty = !state.all_pieces();
+        for kind in PieceType::POCKET {
+            if state.pockets[color.idx()][kind as usize] == 0 { continue }
+            let targets = if kind == PieceType::Pawn {
+                empty & !(masks::RANK_1 | masks::RANK_8)
             } else {
-                GameMove::new(move_sq+16, move_sq, MoveType::DoublePawnPush)
+                empty
             };
-            move_list.push(push_move)
+            for drop_sq in targets {
+                move_list.push(GameMove::new_drop(kind, drop_sq));
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+    use crate::square::Square;
+    use crate::variant::{ Crazyhouse, Variant };
+
+    /// Get the (from, to) squares of the moves in the MoveList.
+    fn move_squares(mut move_list: MoveList) -> Vec<(Square, Square)> {
+        let mut squares = Vec::new();
+        while let Some(game_move) = move_list.pop() {
+            squares.push((game_move.fromsquare(), game_move.tosquare()));
+        }
+        squares.sort();
+        squares
+    }
+
+    #[test]
+    fn test_slider_attacks() {
+        let occupancy: Bitboard = [Square::D4, Square::D6, Square::F4, Square::B2]
+            .into_iter()
+            .collect();
+        let rook: Bitboard = [
+            Square::D5, Square::D6, Square::D3, Square::D2, Square::D1, Square::E4, Square::F4,
+            Square::C4, Square::B4, Square::A4,
+        ].into_iter().collect();
+        assert_eq!(rook_attacks(Square::D4, occupancy), rook);
+
+        let bishop = bishop_attacks(Square::D4, occupancy);
+        assert!(bishop.contains(Square::B2) && !bishop.contains(Square::A1));
+        assert!(bishop.contains(Square::H8) && bishop.contains(Square::G1));
+        assert_eq!(bishop.popcount(), 12);
+        assert_eq!(queen_attacks(Square::D4, occupancy), rook | bishop);
+    }
+
+    #[test]
+    fn test_in_check() {
+        let state = parse_fen("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 1");
+        assert!(!in_check(&state, Color::White));
+        assert!(is_square_attacked(&state, Square::H4, Color::Black));
+        assert!(is_square_attacked(&state, Square::F3, Color::White));
+        assert!(is_square_attacked(&state, Square::E4, Color::White));
+        assert!(!is_square_attacked(&state, Square::E5, Color::White));
+
+        let mated = parse_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 0 1");
+        assert!(in_check(&mated, Color::White));
+        assert!(!in_check(&mated, Color::Black));
+
+        let knight_check = parse_fen("4k3/8/3N4/8/8/8/8/4K3 b - - 0 1");
+        assert!(in_check(&knight_check, Color::Black));
+        let pawn_check = parse_fen("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1");
+        assert!(in_check(&pawn_check, Color::White));
+        let blocked = parse_fen("4k3/4p3/8/8/4R3/8/8/4K3 b - - 0 1");
+        assert!(!in_check(&blocked, Color::Black));
+    }
+
+    #[test]
+    fn test_is_pseudo_legal() {
+        let state = parse_fen("r3k2r/1P6/8/3pP3/8/8/8/RN2K2R w KQkq d6 0 1");
+        let legal = |uci: &str| {
+            GameMove::from_uci(uci, &state, false).is_ok_and(|m| is_pseudo_legal(&state, m))
+        };
+        for uci in ["e1g1", "h1h8", "b1c3", "e5d6", "e5e6", "b7a8q", "b7b8n", "e1d2", "a1a8"] {
+            assert!(legal(uci), "{}", uci);
+        }
+        for uci in ["e1c1", "a1h8", "b1b3", "e5e7", "e5d4", "b7b8", "b7c8q", "h1h3q", "P@e4"] {
+            assert!(!legal(uci), "{}", uci);
         }
+        assert!(!is_pseudo_legal(&state, GameMove::new(Square::A1, Square::A3, MoveType::Capture)));
+        assert!(!is_pseudo_legal(&state, GameMove::new(Square::E5, Square::E7, MoveType::Quiet)));
+
+        let state = Crazyhouse.parse_fen("7k/8/8/8/8/8/8/K7[Pn] w - - 0 1").unwrap();
+        assert!(is_pseudo_legal(&state, GameMove::new_drop(PieceType::Pawn, Square::E4)));
+        assert!(!is_pseudo_legal(&state, GameMove::new_drop(PieceType::Pawn, Square::E8)));
+        assert!(!is_pseudo_legal(&state, GameMove::new_drop(PieceType::Knight, Square::E4)));
+        assert!(!is_pseudo_legal(&state, GameMove::new_drop(PieceType::Pawn, Square::A1)));
+    }
+
+    #[test]
+    fn test_append_drops() {
+        let mut move_list = MoveList::new();
+        let state = Crazyhouse.parse_fen("7k/8/8/8/8/8/8/K7[PPNq] w - - 0 1").unwrap();
+        move_gen_utils::append_drops(&mut move_list, &state);
+
+        let mut drops = Vec::new();
+        while let Some(game_move) = move_list.pop() {
+            drops.push((game_move.drop_kind().unwrap(), game_move.tosquare()));
+        }
+        assert_eq!(drops.len(), 48 + 62);
+        assert!(drops.contains(&(PieceType::Knight, Square::H1)));
+        assert!(!drops.contains(&(PieceType::Pawn, Square::H1)));
+        assert!(!drops.contains(&(PieceType::Knight, Square::A1)));
+        assert!(drops.iter().all(|&(kind, _)| kind != PieceType::Queen));
+    }
+
+    /// Get the (from, to) squares of the castles for the side to move in the given position.
+    fn castles(fen: &str) -> Vec<(Square, Square)> {
+        let mut move_list = MoveList::new();
+        move_gen_utils::append_castles(&mut move_list, &parse_fen(fen));
+        move_squares(move_list)
+    }
+
+    #[test]
+    fn test_append_castles() {
+        let standard = castles("r3k2r/8/8/8/8/8/8/R3K1NR w KQkq - 0 1");
+        assert_eq!(standard, vec![(Square::E1, Square::C1)]);
+
+        // Chess960: the kingside rook starts on the king's destination square, and the queenside
+        // castle is blocked by the b1 knight even though the king doesn't cross b1.
+        assert_eq!(castles("4k3/8/8/8/8/8/8/RN3KR1 w GA - 0 1"), vec![(Square::F1, Square::G1)]);
+    }
+
+    #[test]
+    fn test_castles_avoid_check() {
+        // Out of check, through check and into check.
+        assert_eq!(castles("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1"), vec![]);
+        assert_eq!(castles("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1"), vec![(Square::E1, Square::C1)]);
+        assert_eq!(castles("4k3/8/8/8/8/8/2r5/R3K2R w KQ - 0 1"), vec![(Square::E1, Square::G1)]);
+
+        // Only the rook crosses b1, so an attack on it doesn't matter.
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/1r6/R3K2R w KQ - 0 1"),
+            vec![(Square::E1, Square::C1), (Square::E1, Square::G1)],
+        );
+
+        // Chess960: the castling rook on b1 shields the king from the a1 queen until it moves.
+        assert_eq!(castles("4k3/8/8/8/8/8/8/qRK5 w B - 0 1"), vec![]);
     }
 }
@@ -1,29 +1,40 @@
-///! This file contains the MoveList struct.
+//! This file contains the MoveList struct.
 use crate::game_move::GameMove;
 
 
-/// A 256-length array to hold a list of GameMoves. Fixed length array (not Vec) to store in the 
-/// stack for faster access.
+/// The most moves a MoveList can hold. Standard chess positions have at most 218 legal moves, but
+/// Crazyhouse drops can add a few hundred more.
+pub const MAX_MOVES: usize = 512;
+
+
+/// A MAX_MOVES-length array to hold a list of GameMoves. Fixed length array (not Vec) to store in
+/// the stack for faster access.
 pub struct MoveList {
-    backing: [GameMove;256],
+    backing: [GameMove; MAX_MOVES],
     size: usize,
 }
 
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MoveList {
     /// Create a new, empty MoveList.
     pub fn new() -> Self {
-        MoveList { backing: [GameMove { data: u16::MAX };256], size: 0 }
+        MoveList { backing: [GameMove { data: u16::MAX }; MAX_MOVES], size: 0 }
     }
 
     /// Add the given GameMove to the end of the MoveList.
     pub fn push(&mut self, elt: GameMove) {
-        self.backing[self.size as usize] = elt;
+        self.backing[self.size] = elt;
         self.size += 1;
     }
 
     /// Returns the last element in the MoveList.
     pub fn peek(&self) -> Option<GameMove> {
-        if self.size <= 0 {
+        if self.size == 0 {
             return None;
         }
         Some(self.backing[self.size-1])
@@ -31,7 +42,7 @@ impl MoveList {
     
     /// Removes & returns the last element in the MoveList.
     pub fn pop(&mut self) -> Option<GameMove> {
-        if self.size <= 0 {
+        if self.size == 0 {
             return None;
         }
         let val = Some(self.backing[self.size-1]);
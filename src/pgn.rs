@@ -0,0 +1,201 @@
+//! This file contains a reader for games in Portable Game Notation (PGN).
+//!
+//! Only what is needed to replay the main line of each game is kept: the tag pairs, the moves in
+//! SAN, and the result. Comments, variations, move numbers and numeric annotation glyphs are
+//! skipped. The moves are not checked here; replaying them is up to the caller.
+use std::iter::Peekable;
+use std::str::Chars;
+use crate::game_state::Color;
+use crate::variant::Outcome;
+
+
+/// A game read from PGN.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /// The moves of the main line, in SAN as written.
+    pub moves: Vec<String>,
+    /// The result, or None if the game is unfinished or its result is unknown ("*").
+    pub result: Option<Outcome>,
+}
+
+impl PgnGame {
+    /// Get the value of the tag with the given name, if the game has it.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+}
+
+
+/// Read every game in the given PGN text. A game ends at its result token, or, if that is
+/// missing, where the next game's tags start; its result is then taken from its Result tag.
+pub fn parse_pgn(pgn: &str) -> Result<Vec<PgnGame>, String> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut in_movetext = false;
+    let mut chars = pgn.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => {},
+            '[' => {
+                if in_movetext {
+                    games.push(finish_game(game));
+                    game = PgnGame::default();
+                    in_movetext = false;
+                }
+                game.tags.push(read_tag(&mut chars)?);
+            },
+            '{' => skip_comment(&mut chars)?,
+            ';' => skip_line(&mut chars),
+            '(' => skip_variation(&mut chars)?,
+            '$' => { read_token(&mut chars); },
+            _ => {
+                let token = format!("{}{}", c, read_token(&mut chars));
+                if let Some(result) = parse_result(&token) {
+                    game.result = result;
+                    games.push(game);
+                    game = PgnGame::default();
+                    in_movetext = false;
+                    continue;
+                }
+
+                // Drop any move number, e.g. "12." or "12...", in front of the move.
+                let san = match token.rfind('.') {
+                    Some(i) if token.starts_with(|c: char| c.is_ascii_digit()) => &token[i + 1..],
+                    _ => token.as_str(),
+                };
+                if !san.is_empty() { game.moves.push(san.to_string()) }
+                in_movetext = true;
+            },
+        }
+    }
+
+    if in_movetext || !game.tags.is_empty() { games.push(finish_game(game)) }
+    Ok(games)
+}
+
+/// Take the result of a game which has no result token from its Result tag.
+fn finish_game(mut game: PgnGame) -> PgnGame {
+    game.result = game.tag("Result").and_then(parse_result).flatten();
+    game
+}
+
+/// Parse a game termination marker, or return None if the token isn't one. An unknown result ("*")
+/// is Some(None).
+fn parse_result(token: &str) -> Option<Option<Outcome>> {
+    match token {
+        "1-0" => Some(Some(Outcome::Win(Color::White))),
+        "0-1" => Some(Some(Outcome::Win(Color::Black))),
+        "1/2-1/2" => Some(Some(Outcome::Draw)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+/// Read the rest of a tag pair after its "[", e.g. `Event "Casual game"]`.
+fn read_tag(chars: &mut Peekable<Chars>) -> Result<(String, String), String> {
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '"' && *c != ']') {
+        name.push(c);
+    }
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    if chars.next() != Some('"') { return Err(format!("Invalid PGN tag: {}", name)) }
+
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => break,
+            Some('\\') => value.extend(chars.next()),
+            Some(c) => value.push(c),
+            None => return Err(format!("Unterminated PGN tag: {}", name)),
+        }
+    }
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    if chars.next() != Some(']') { return Err(format!("Invalid PGN tag: {}", name)) }
+    Ok((name, value))
+}
+
+/// Read the rest of a move or other token, up to whitespace or the start of a comment, variation
+/// or tag.
+fn read_token(chars: &mut Peekable<Chars>) -> String {
+    let mut token = String::new();
+    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"{};()[$".contains(*c)) {
+        token.push(c);
+    }
+    token
+}
+
+/// Skip the rest of a "{...}" comment.
+fn skip_comment(chars: &mut Peekable<Chars>) -> Result<(), String> {
+    if chars.any(|c| c == '}') { Ok(()) } else { Err("Unterminated PGN comment.".to_string()) }
+}
+
+/// Skip the rest of a ";" comment, which runs to the end of the line.
+fn skip_line(chars: &mut Peekable<Chars>) {
+    chars.find(|&c| c == '\n');
+}
+
+/// Skip the rest of a "(...)" variation, along with any variations and comments inside it.
+fn skip_variation(chars: &mut Peekable<Chars>) -> Result<(), String> {
+    let mut depth = 1;
+    while depth > 0 {
+        match chars.next() {
+            Some('(') => depth += 1,
+            Some(')') => depth -= 1,
+            Some('{') => skip_comment(chars)?,
+            Some(';') => skip_line(chars),
+            Some(_) => {},
+            None => return Err("Unterminated PGN variation.".to_string()),
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pgn() {
+        let pgn = r#"
+[Event "Casual \"blitz\" game"]
+[White "Alice"]
+[Result "1-0"]
+
+1. e4 e5 {A comment (with brackets)} 2. Nf3 $1 Nc6 (2... d6 3. d4 (3. Bc4)) 3.Bb5 ; to the end
+3... a6 1-0
+
+[Event "Second"]
+1. d4 d5 *
+"#;
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("Event"), Some("Casual \"blitz\" game"));
+        assert_eq!(games[0].tag("White"), Some("Alice"));
+        assert_eq!(games[0].tag("Black"), None);
+        assert_eq!(games[0].moves, ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(games[0].result, Some(Outcome::Win(Color::White)));
+        assert_eq!(games[1].moves, ["d4", "d5"]);
+        assert_eq!(games[1].result, None);
+    }
+
+    #[test]
+    fn test_result_from_tag() {
+        let pgn = "[Result \"1/2-1/2\"]\n1. e4 e5\n[Result \"0-1\"]\n1. O-O-O";
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].result, Some(Outcome::Draw));
+        assert_eq!(games[1].result, Some(Outcome::Win(Color::Black)));
+        assert_eq!(games[1].moves, ["O-O-O"]);
+    }
+
+    #[test]
+    fn test_malformed_pgn() {
+        assert!(parse_pgn("[Event \"Unterminated]").is_err());
+        assert!(parse_pgn("[Event Unquoted]").is_err());
+        assert!(parse_pgn("1. e4 {unterminated").is_err());
+        assert!(parse_pgn("1. e4 (1. d4").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! This file contains the builder for Polyglot opening books, which replays PGN games and records
+//! the move played from each position in their openings.
+//!
+//! As in Polyglot's own make-book, a move scores 2 points for each game won by the side which
+//! played it and 1 point for each draw, so its weight grows with both its results and how often
+//! it was played. Moves only ever played in lost games get no weight and are left out.
+use std::collections::HashMap;
+use std::fs;
+use crate::chess960;
+use crate::fen;
+use crate::game_state::{ Game, GameState };
+use crate::pgn::PgnGame;
+use crate::san;
+use crate::variant::Outcome;
+use super::{ encode_move, key, BookEntry };
+
+
+/// Which games and moves go into a book.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BookOptions {
+    /// Number of plies recorded from the start of each game.
+    pub max_ply: usize,
+    /// Number of games a move must be played in to be kept.
+    pub min_count: u32,
+    /// Rating both players need, from the WhiteElo and BlackElo tags. Games without the tags are
+    /// skipped when this is set.
+    pub min_elo: Option<u32>,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        BookOptions { max_ply: 20, min_count: 3, min_elo: None }
+    }
+}
+
+
+/// How often a move was played from a position, and the points scored with it.
+#[derive(Copy, Clone, Debug, Default)]
+struct MoveStats {
+    count: u32,
+    points: u32,
+}
+
+
+/// Collects the moves of PGN games and turns them into Polyglot book entries.
+pub struct BookBuilder {
+    options: BookOptions,
+    moves: HashMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    /// Make a new, empty BookBuilder with the given options.
+    pub fn new(options: BookOptions) -> Self {
+        BookBuilder { options, moves: HashMap::new() }
+    }
+
+    /// Record the opening moves of the given game. Returns whether the game was used; games with
+    /// no result, or whose players are rated below the minimum, are skipped.
+    ///
+    /// The moves are replayed from the game's FEN tag if it has one, or else the standard start
+    /// position. Nothing is recorded if a move doesn't parse or isn't legal.
+    pub fn add_game(&mut self, game: &PgnGame) -> Result<bool, String> {
+        let Some(outcome) = game.result else { return Ok(false) };
+        if !self.meets_min_elo(game) { return Ok(false) }
+
+        let mut replay = Game::new(start_state(game)?);
+        let mut played = Vec::new();
+        for san_move in game.moves.iter().take(self.options.max_ply) {
+            let state = replay.current_state();
+            let game_move = san::parse_san(san_move, &state)?;
+            let points = match outcome {
+                Outcome::Win(color) if color == state.side_to_move => 2,
+                Outcome::Win(_) => 0,
+                Outcome::Draw => 1,
+            };
+            played.push(((key(&state), encode_move(&state, game_move)), points));
+            replay.make(game_move);
+        }
+
+        for (book_move, points) in played {
+            let stats = self.moves.entry(book_move).or_default();
+            stats.count += 1;
+            stats.points += points;
+        }
+        Ok(true)
+    }
+
+    /// Get the book entries for the moves recorded so far, sorted by key and then by weight, the
+    /// heaviest first. Weights are scaled down to fit in 16 bits if needed.
+    pub fn entries(&self) -> Vec<BookEntry> {
+        let kept: Vec<(&(u64, u16), &MoveStats)> = self.moves.iter()
+            .filter(|(_, stats)| stats.count >= self.options.min_count && stats.points > 0)
+            .collect();
+        let max_points = kept.iter().map(|(_, stats)| stats.points as u64).max().unwrap_or(0);
+        let scale = |points: u32| {
+            if max_points <= u16::MAX as u64 { return points as u16 }
+            (points as u64 * u16::MAX as u64 / max_points).max(1) as u16
+        };
+
+        let mut entries: Vec<BookEntry> = kept.into_iter()
+            .map(|(&(key, raw_move), stats)| {
+                BookEntry { key, raw_move, weight: scale(stats.points), learn: 0 }
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.key, u16::MAX - entry.weight, entry.raw_move));
+        entries
+    }
+
+    /// Serialize the book to the Polyglot on-disk format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries().iter().flat_map(BookEntry::to_bytes).collect()
+    }
+
+    /// Write the book to the given path.
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| format!("Could not write book {}: {}", path, e))
+    }
+
+    /// Returns whether both players of the given game are rated at least the minimum Elo.
+    fn meets_min_elo(&self, game: &PgnGame) -> bool {
+        let Some(min_elo) = self.options.min_elo else { return true };
+        ["WhiteElo", "BlackElo"].iter().all(|tag| {
+            game.tag(tag).and_then(|elo| elo.parse::<u32>().ok()).is_some_and(|elo| elo >= min_elo)
+        })
+    }
+}
+
+
+/// Get the position the given game starts from.
+fn start_state(game: &PgnGame) -> Result<GameState, String> {
+    match game.tag("FEN") {
+        Some(fen) => {
+            let state = fen::try_parse_fen(fen)?;
+            state.validate()?;
+            Ok(state)
+        },
+        None => chess960::start_position(chess960::STANDARD_INDEX),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_pgn;
+    use crate::polyglot::Book;
+    use crate::square::Square;
+
+    const STARTING_KEY: u64 = 0x463b96181691fc9c;
+
+    fn build(pgn: &str, options: BookOptions) -> BookBuilder {
+        let mut builder = BookBuilder::new(options);
+        for game in parse_pgn(pgn).unwrap() {
+            builder.add_game(&game).unwrap();
+        }
+        builder
+    }
+
+    #[test]
+    fn test_weights_and_min_count() {
+        let pgn = "1. e4 e5 1-0\n1. e4 c5 1/2-1/2\n1. d4 d5 0-1\n1. d4 Nf6 1-0\n1. c4 1-0";
+        let options = BookOptions { max_ply: 2, min_count: 2, min_elo: None };
+        let book = Book::from_bytes(&build(pgn, options).to_bytes()).unwrap();
+
+        // e4 scored 2 + 1 and d4 0 + 2; c4 and every black move were only played once.
+        let entries = book.entries(STARTING_KEY);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].raw_move, entries[0].weight), (12 << 6 | 28, 3));
+        assert_eq!((entries[1].raw_move, entries[1].weight), (11 << 6 | 27, 2));
+        assert_eq!(book.len(), 2);
+
+        let state = chess960::start_position(chess960::STANDARD_INDEX).unwrap();
+        let best = book.best_move(&state).unwrap();
+        assert_eq!((best.fromsquare(), best.tosquare()), (Square::E2, Square::E4));
+    }
+
+    #[test]
+    fn test_skipped_games() {
+        let pgn = "\
+[WhiteElo \"2500\"]\n[BlackElo \"2400\"]\n1. e4 1-0\n\
+[WhiteElo \"2500\"]\n[BlackElo \"1500\"]\n1. d4 1-0\n\
+[WhiteElo \"2500\"]\n1. c4 1-0\n\
+[WhiteElo \"2500\"]\n[BlackElo \"2400\"]\n1. Nf3 *";
+        let options = BookOptions { max_ply: 1, min_count: 1, min_elo: Some(2000) };
+        let entries = build(pgn, options).entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].raw_move, 12 << 6 | 28);
+
+        let mut builder = BookBuilder::new(BookOptions { max_ply: 2, ..options });
+        let illegal = "[WhiteElo \"2500\"]\n[BlackElo \"2400\"]\n1. e4 e4 1-0";
+        assert!(builder.add_game(&parse_pgn(illegal).unwrap()[0]).is_err());
+        assert!(builder.entries().is_empty());
+    }
+
+    #[test]
+    fn test_castles_and_fen_tag() {
+        let pgn = "[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n1. O-O 1-0";
+        let options = BookOptions { max_ply: 1, min_count: 1, min_elo: None };
+        let entries = build(pgn, options).entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].raw_move, 4 << 6 | 7);
+        assert_eq!(entries[0].key, key(&fen::parse_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1")));
+    }
+}
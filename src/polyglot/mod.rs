@@ -0,0 +1,420 @@
+//! This file contains functions for reading Polyglot (.bin) opening books and converting book
+//! entries into GameMoves. Books are written by the builder in `builder`.
+//!
+//! See http://hgm.nubati.net/book_format.html for the book format and Polyglot keys.
+use std::fs;
+use crate::bits::masks;
+use crate::game_state::{ self, CastleRights, Color, GameState, Piece, PieceType };
+use crate::square::{ File, Square };
+use crate::game_move::{ GameMove, MoveType };
+
+pub mod builder;
+mod random;
+
+use random::RANDOM64;
+
+
+/// Size in bytes of a single book entry.
+const ENTRY_SIZE: usize = 16;
+
+/// Bitmasks for getting info from a Polyglot move.
+const SQUARE_BITMASK: u16 = 63;
+const PROMO_BITMASK: u16 = 7;
+
+
+/// Offsets of each part of the Random64 array.
+const CASTLE_OFFSET: usize = 768;
+const EP_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+
+/// Get the Polyglot key of the given GameState, computed from scratch. This is separate from
+/// `GameState::hash`, which uses the engine's own Zobrist keys.
+///
+/// The en-passant file is only hashed if a pawn of the side to move could capture en passant,
+/// whether or not the capture would be legal.
+pub fn key(state: &GameState) -> u64 {
+    let pieces = Square::all()
+        .filter_map(|sq| state.occupying_piece(sq).map(|piece| piece_key(piece, sq)))
+        .fold(0, |key, piece_key| key ^ piece_key);
+    let turn = if state.side_to_move == Color::White { turn_key() } else { 0 };
+    pieces
+        ^ castle_rights_key(state.castlerights)
+        ^ ep_square_key(state, state.ep_square)
+        ^ turn
+}
+
+/// Get the Random64 value for the given piece on the given square.
+pub fn piece_key(piece: Piece, sq: Square) -> u64 {
+    let kind = match piece.kind() {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+    let color = if piece.color() == Color::White { 1 } else { 0 };
+    RANDOM64[64 * (2 * kind + color) + sq.idx()]
+}
+
+/// Get the Random64 value for the castle right at the given castlerights index.
+pub fn castle_key(right: usize) -> u64 {
+    RANDOM64[CASTLE_OFFSET + right]
+}
+
+/// Get the part of the Polyglot key for the given castle rights.
+pub fn castle_rights_key(castlerights: CastleRights) -> u64 {
+    (0..4)
+        .filter(|&right| castlerights.has(right))
+        .fold(0, |key, right| key ^ castle_key(right))
+}
+
+/// Get the part of the Polyglot key for the given en-passant square in the given GameState: the
+/// value for its file if a pawn of the side to move could capture on it, or 0.
+pub fn ep_square_key(state: &GameState, ep_square: Option<Square>) -> u64 {
+    let Some(ep_square) = ep_square else { return 0 };
+    let color = state.side_to_move;
+    let capturers = masks::PAWN_ATTACKS[(!color).idx()][ep_square.idx()]
+        & state.pieces(color, PieceType::Pawn);
+    if capturers.is_empty() { 0 } else { ep_key(ep_square.file()) }
+}
+
+/// Get the Random64 value for an en-passant square on the given file.
+pub fn ep_key(file: File) -> u64 {
+    RANDOM64[EP_OFFSET + file.idx()]
+}
+
+/// Get the Random64 value for white to move.
+pub fn turn_key() -> u64 {
+    RANDOM64[TURN_OFFSET]
+}
+
+/// Encode the given move on the given GameState as a Polyglot move; the reverse of
+/// `BookEntry::to_game_move`. Castles are encoded as the king capturing its own rook.
+pub fn encode_move(state: &GameState, game_move: GameMove) -> u16 {
+    let tosquare = match game_move.move_type() {
+        move_type @ (MoveType::KingCastle | MoveType::QueenCastle) => {
+            let right = game_state::castle_right_idx(move_type, state.side_to_move);
+            state.castle_files().rook_square(right)
+        },
+        _ => game_move.tosquare(),
+    };
+    let promo = match game_move.promo_kind() {
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(PieceType::Queen) => 4,
+        _ => 0,
+    };
+    promo << 12 | (game_move.fromsquare().idx() as u16) << 6 | tosquare.idx() as u16
+}
+
+
+/// A single entry in a Polyglot book. On disk, each entry is 16 bytes, stored big-endian.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    /// Parse a BookEntry from its 16-byte on-disk representation.
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> Self {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    /// Serialize this BookEntry to its 16-byte on-disk representation.
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+
+    /// Convert this entry's move into a GameMove on the given GameState. Returns None if the move
+    /// does not make sense on the given GameState (e.g. there is no piece on the fromsquare).
+    ///
+    /// Polyglot encodes castling as the king capturing its own rook (e.g. e1h1), which is
+    /// converted to the king's actual destination square (e.g. e1g1).
+    pub fn to_game_move(&self, state: &GameState) -> Option<GameMove> {
+        let fromsquare = Square::new(((self.raw_move >> 6) & SQUARE_BITMASK) as u8);
+        let tosquare = Square::new((self.raw_move & SQUARE_BITMASK) as u8);
+        let promo = (self.raw_move >> 12) & PROMO_BITMASK;
+
+        let moving = state.occupying_piece(fromsquare)?;
+        let captured = state.occupying_piece(tosquare);
+
+        // Castling: the king "captures" its own castling rook, which also covers Chess960.
+        let color = moving.color();
+        let castle_files = state.castle_files();
+        if moving.kind() == PieceType::King
+            && fromsquare == castle_files.king_square(color)
+            && captured == Some(Piece::new(color, PieceType::Rook))
+        {
+            let move_type = if tosquare.file() > fromsquare.file() {
+                MoveType::KingCastle
+            } else {
+                MoveType::QueenCastle
+            };
+            let right = game_state::castle_right_idx(move_type, color);
+            if tosquare != castle_files.rook_square(right) { return None }
+            let (king_from, king_to, _, _) = state.castle_squares(move_type, color);
+            return Some(GameMove::new(king_from, king_to, move_type));
+        }
+
+        let is_capture = captured.is_some();
+        let is_pawn = moving.kind() == PieceType::Pawn;
+        let move_type = match (promo, is_capture) {
+            (1, false) => MoveType::KnightPromo,
+            (2, false) => MoveType::BishopPromo,
+            (3, false) => MoveType::RookPromo,
+            (4, false) => MoveType::QueenPromo,
+            (1, true) => MoveType::KnightPromoCapture,
+            (2, true) => MoveType::BishopPromoCapture,
+            (3, true) => MoveType::RookPromoCapture,
+            (4, true) => MoveType::QueenPromoCapture,
+            (0, true) => MoveType::Capture,
+            (0, false) if is_pawn && state.ep_square == Some(tosquare) => MoveType::EpCapture,
+            (0, false) if is_pawn && fromsquare.rank().distance(tosquare.rank()) == 2 =>
+                MoveType::DoublePawnPush,
+            (0, false) => MoveType::Quiet,
+            _ => return None,
+        };
+        Some(GameMove::new(fromsquare, tosquare, move_type))
+    }
+}
+
+
+/// A Polyglot opening book, held in memory as a list of entries sorted by key.
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    /// Read the Polyglot book at the given path.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Could not read book {}: {}", path, e))?;
+        Book::from_bytes(&bytes)
+    }
+
+    /// Parse a Polyglot book from its raw bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err("Invalid book; size is not a multiple of the entry size.".to_string());
+        }
+        let entries: Vec<BookEntry> = bytes.chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry::from_bytes(chunk.try_into().unwrap()))
+            .collect();
+        if entries.windows(2).any(|pair| pair[0].key > pair[1].key) {
+            return Err("Invalid book; entries are not sorted by key.".to_string());
+        }
+        Ok(Book { entries })
+    }
+
+    /// Get the number of entries in this Book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether this Book has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get all entries for the given Polyglot key, found by binary search.
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// Get the book move with the highest weight for the given state, or None if the position is
+    /// not in the book.
+    pub fn best_move(&self, state: &GameState) -> Option<GameMove> {
+        self.entries(key(state)).iter()
+            .filter(|entry| entry.weight > 0)
+            // Prefer the first of several equally-weighted entries.
+            .rev()
+            .max_by_key(|entry| entry.weight)
+            .and_then(|entry| entry.to_game_move(state))
+    }
+
+    /// Pick a book move for the given state at random, with probability proportional to its
+    /// weight. The caller supplies the random value.
+    pub fn weighted_move(&self, state: &GameState, random: u64) -> Option<GameMove> {
+        let entries = self.entries(key(state));
+        let total_weight: u64 = entries.iter().map(|entry| entry.weight as u64).sum();
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut pick = random % total_weight;
+        for entry in entries {
+            if pick < entry.weight as u64 {
+                return entry.to_game_move(state);
+            }
+            pick -= entry.weight as u64;
+        }
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const STARTING_KEY: u64 = 0x463b96181691fc9c;
+
+    /// Make a Polyglot move from from/to squares and a promotion code.
+    fn raw_move(fromsquare: u16, tosquare: u16, promo: u16) -> u16 {
+        promo << 12 | fromsquare << 6 | tosquare
+    }
+
+    fn entry(key: u64, raw_move: u16, weight: u16) -> BookEntry {
+        BookEntry { key, raw_move, weight, learn: 0 }
+    }
+
+    fn book_from(entries: &[BookEntry]) -> Book {
+        let bytes: Vec<u8> = entries.iter().flat_map(|e| e.to_bytes()).collect();
+        Book::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_entry_round_trip() {
+        let e = BookEntry { key: STARTING_KEY, raw_move: raw_move(12, 28, 0), weight: 7, learn: 9 };
+        assert_eq!(BookEntry::from_bytes(&e.to_bytes()), e);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let book = book_from(&[
+            entry(1, raw_move(12, 28, 0), 1),
+            entry(STARTING_KEY, raw_move(12, 28, 10), 10),
+            entry(STARTING_KEY, raw_move(11, 27, 0), 5),
+            entry(u64::MAX, raw_move(6, 21, 0), 1),
+        ]);
+        assert_eq!(book.len(), 4);
+        assert_eq!(book.entries(STARTING_KEY).len(), 2);
+        assert_eq!(book.entries(2).len(), 0);
+        assert_eq!(book.entries(u64::MAX).len(), 1);
+    }
+
+    #[test]
+    fn test_unsorted_book_rejected() {
+        let bytes: Vec<u8> = [entry(2, 0, 1), entry(1, 0, 1)].iter()
+            .flat_map(|e| e.to_bytes())
+            .collect();
+        assert!(Book::from_bytes(&bytes).is_err());
+        assert!(Book::from_bytes(&bytes[..15]).is_err());
+    }
+
+    #[test]
+    fn test_move_selection() {
+        let state = parse_fen(STARTING_FEN);
+        let book = book_from(&[
+            entry(STARTING_KEY, raw_move(12, 28, 0), 3),
+            entry(STARTING_KEY, raw_move(11, 27, 0), 5),
+        ]);
+
+        let best = book.best_move(&state).unwrap();
+        assert_eq!((best.fromsquare(), best.tosquare()), (Square::D2, Square::D4));
+
+        let first = book.weighted_move(&state, 2).unwrap();
+        assert_eq!((first.fromsquare(), first.tosquare()), (Square::E2, Square::E4));
+        let second = book.weighted_move(&state, 3).unwrap();
+        assert_eq!((second.fromsquare(), second.tosquare()), (Square::D2, Square::D4));
+
+        let out_of_book = state.make_copy(best);
+        assert!(book.best_move(&out_of_book).is_none());
+        assert!(book.weighted_move(&out_of_book, 0).is_none());
+    }
+
+    #[test]
+    /// Test against the keys published with the Polyglot book format.
+    fn test_keys() {
+        let cases = [
+            (STARTING_FEN, STARTING_KEY),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+            ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+            ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+            ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+            ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3", 0x652a607ca3f242c1),
+            ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4", 0x00fdd303c946bdd9),
+            ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+            ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+        ];
+        for (fen, expected) in cases {
+            assert_eq!(key(&parse_fen(fen)), expected, "{}", fen);
+        }
+
+        // The e.p. square set by make only counts once a pawn can capture on it.
+        let mut state = parse_fen(STARTING_FEN);
+        state.make(GameMove::new(Square::E2, Square::E4, MoveType::DoublePawnPush));
+        assert_eq!(key(&state), 0x823c9b50fd114196);
+        state.make(GameMove::new(Square::D7, Square::D5, MoveType::DoublePawnPush));
+        assert_eq!(key(&state), 0x0756b94461c50fb0);
+    }
+
+    #[test]
+    fn test_move_conversion() {
+        let state = parse_fen(STARTING_FEN);
+        let double_push = entry(0, raw_move(12, 28, 0), 1).to_game_move(&state).unwrap();
+        assert!(double_push.move_type() == MoveType::DoublePawnPush);
+        let knight = entry(0, raw_move(6, 21, 0), 1).to_game_move(&state).unwrap();
+        assert!(knight.move_type() == MoveType::Quiet);
+        assert!(entry(0, raw_move(20, 28, 0), 1).to_game_move(&state).is_none());
+
+        let state = parse_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        let castle = entry(0, raw_move(4, 7, 0), 1).to_game_move(&state).unwrap();
+        assert!(castle.move_type() == MoveType::KingCastle);
+        assert_eq!((castle.fromsquare(), castle.tosquare()), (Square::E1, Square::G1));
+        let castle = entry(0, raw_move(4, 0, 0), 1).to_game_move(&state).unwrap();
+        assert!(castle.move_type() == MoveType::QueenCastle);
+        assert_eq!((castle.fromsquare(), castle.tosquare()), (Square::E1, Square::C1));
+
+        let ep = entry(0, raw_move(36, 43, 0), 1).to_game_move(&state).unwrap();
+        assert!(ep.move_type() == MoveType::EpCapture);
+        let promo = entry(0, raw_move(49, 57, 4), 1).to_game_move(&state).unwrap();
+        assert!(promo.move_type() == MoveType::QueenPromo);
+        let promo = entry(0, raw_move(49, 56, 1), 1).to_game_move(&state).unwrap();
+        assert!(promo.move_type() == MoveType::KnightPromoCapture);
+
+        // Chess960: castles use the position's king and rook files.
+        let state = parse_fen("1r1k1r1r/8/8/8/8/8/8/RK4R1 w KQfq - 0 1");
+        let castle = entry(0, raw_move(1, 6, 0), 1).to_game_move(&state).unwrap();
+        assert!(castle.move_type() == MoveType::KingCastle);
+        assert_eq!((castle.fromsquare(), castle.tosquare()), (Square::B1, Square::G1));
+        let castle = entry(0, raw_move(1, 0, 0), 1).to_game_move(&state).unwrap();
+        assert!(castle.move_type() == MoveType::QueenCastle);
+        assert_eq!((castle.fromsquare(), castle.tosquare()), (Square::B1, Square::C1));
+    }
+
+    #[test]
+    fn test_encode_move() {
+        let state = parse_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        for raw in [raw_move(4, 7, 0), raw_move(4, 0, 0), raw_move(36, 43, 0), raw_move(49, 57, 4),
+                    raw_move(49, 56, 1), raw_move(0, 8, 0)] {
+            let game_move = entry(0, raw, 1).to_game_move(&state).unwrap();
+            assert_eq!(encode_move(&state, game_move), raw);
+        }
+
+        let state = parse_fen("1r1k1r1r/8/8/8/8/8/8/RK4R1 w KQfq - 0 1");
+        for raw in [raw_move(1, 6, 0), raw_move(1, 0, 0)] {
+            let game_move = entry(0, raw, 1).to_game_move(&state).unwrap();
+            assert_eq!(encode_move(&state, game_move), raw);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! This file contains the parser for moves in standard algebraic notation (SAN), e.g. "Nf3",
+//! "exd5", "e8=Q+", "O-O" or "N@f3".
+//!
+//! SAN only names the moving piece and its destination, so a move is found by trying every piece
+//! of that kind which matches the disambiguation and keeping the legal ones.
+use crate::game_move::{ GameMove, MoveType };
+use crate::game_state::{ GameState, PieceType };
+use crate::move_gen;
+use crate::square::{ Square, File, Rank };
+
+
+/// Parse a move in SAN for the side to move in the given GameState. Check and annotation marks
+/// ("+", "#", "!", "?") are ignored, and a capture may be written with or without the "x".
+///
+/// The move must be legal by the rules of standard chess, except that drops are accepted whenever
+/// the piece is in the mover's pocket; whether the variant has drops is up to the caller.
+pub fn parse_san(san: &str, state: &GameState) -> Result<GameMove, String> {
+    let invalid = || format!("Invalid move: {}", san);
+    let stripped = san.trim_end_matches(['+', '#', '!', '?']);
+    if !stripped.is_ascii() { return Err(invalid()) }
+
+    let candidates = match stripped {
+        "O-O" | "0-0" => vec![castle(state, MoveType::KingCastle)],
+        "O-O-O" | "0-0-0" => vec![castle(state, MoveType::QueenCastle)],
+        // A pawn drop may leave out the "P".
+        _ if stripped.starts_with('@') => {
+            vec![GameMove::from_uci(&format!("P{}", stripped), state, false)?]
+        },
+        _ if stripped.contains('@') => vec![GameMove::from_uci(stripped, state, false)?],
+        _ => piece_moves(stripped, state).ok_or_else(invalid)?,
+    };
+
+    let legal: Vec<GameMove> = candidates.into_iter().filter(|&m| is_legal(state, m)).collect();
+    match legal[..] {
+        [game_move] => Ok(game_move),
+        [] => Err(format!("Illegal move: {}", san)),
+        _ => Err(format!("Ambiguous move: {}", san)),
+    }
+}
+
+/// Get the castle of the given type for the side to move, whether or not it is allowed.
+fn castle(state: &GameState, move_type: MoveType) -> GameMove {
+    let (king_from, king_to, _, _) = state.castle_squares(move_type, state.side_to_move);
+    GameMove::new(king_from, king_to, move_type)
+}
+
+/// Get the moves of the side to move's pieces which match the given SAN string (without check
+/// marks), or None if it is malformed. The moves aren't checked for legality.
+fn piece_moves(san: &str, state: &GameState) -> Option<Vec<GameMove>> {
+    let (kind, rest) = match san.chars().next()? {
+        'N' => (PieceType::Knight, &san[1..]),
+        'B' => (PieceType::Bishop, &san[1..]),
+        'R' => (PieceType::Rook, &san[1..]),
+        'Q' => (PieceType::Queen, &san[1..]),
+        'K' => (PieceType::King, &san[1..]),
+        _ => (PieceType::Pawn, san),
+    };
+
+    // A promotion is written as "e8=Q", or sometimes "e8Q".
+    let (rest, promo) = match rest.char_indices().last()? {
+        (i, c @ ('N' | 'B' | 'R' | 'Q')) if kind == PieceType::Pawn => {
+            (rest[..i].strip_suffix('=').unwrap_or(&rest[..i]), Some(c.to_ascii_lowercase()))
+        },
+        _ => (rest, None),
+    };
+    if rest.len() < 2 { return None }
+    let (rest, to_str) = rest.split_at(rest.len() - 2);
+    let tosquare: Square = to_str.parse().ok()?;
+
+    // What is left is the disambiguation: a file, a rank or both, and perhaps a capture mark.
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in rest.strip_suffix('x').unwrap_or(rest).chars() {
+        if let Ok(file) = c.to_string().parse::<File>() {
+            from_file = Some(file);
+        } else {
+            from_rank = Some(c.to_string().parse::<Rank>().ok()?);
+        }
+    }
+
+    let promo = promo.map(String::from).unwrap_or_default();
+    let moves = state.pieces(state.side_to_move, kind)
+        .into_iter()
+        .filter(|sq| from_file.is_none_or(|file| sq.file() == file))
+        .filter(|sq| from_rank.is_none_or(|rank| sq.rank() == rank))
+        .filter_map(|sq| {
+            GameMove::from_uci(&format!("{}{}{}", sq, tosquare, promo), state, false).ok()
+        })
+        // Castles are only written as O-O or O-O-O.
+        .filter(|m| !matches!(m.move_type(), MoveType::KingCastle | MoveType::QueenCastle))
+        // A pawn capture always names the file it starts on, and a pawn push never does.
+        .filter(|m| kind != PieceType::Pawn || m.is_capture() == from_file.is_some())
+        .collect();
+    Some(moves)
+}
+
+/// Returns whether the given move is pseudo-legal and doesn't leave the mover's king in check.
+fn is_legal(state: &GameState, game_move: GameMove) -> bool {
+    move_gen::is_pseudo_legal(state, game_move)
+        && !move_gen::in_check(&state.make_copy(game_move), state.side_to_move)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+
+    /// Parse the SAN move in the given position and write it in UCI notation.
+    fn san_to_uci(fen: &str, san: &str) -> Result<String, String> {
+        let state = parse_fen(fen);
+        parse_san(san, &state).map(|game_move| game_move.to_uci(&state, false))
+    }
+
+    #[test]
+    fn test_parse_san() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san_to_uci(start, "e4"), Ok("e2e4".to_string()));
+        assert_eq!(san_to_uci(start, "Nf3!"), Ok("g1f3".to_string()));
+        assert!(san_to_uci(start, "e5").is_err());
+        assert!(san_to_uci(start, "Nd4").is_err());
+        assert!(san_to_uci(start, "O-O").is_err());
+        assert!(san_to_uci(start, "Zz9").is_err());
+
+        let fen = "r3k2r/1P6/8/3p4/4P3/8/8/RN2K1NR w KQkq - 0 1";
+        assert_eq!(san_to_uci(fen, "exd5"), Ok("e4d5".to_string()));
+        assert_eq!(san_to_uci(fen, "bxa8=N+"), Ok("b7a8n".to_string()));
+        assert_eq!(san_to_uci(fen, "b8Q"), Ok("b7b8q".to_string()));
+        assert_eq!(san_to_uci(fen, "O-O"), Err("Illegal move: O-O".to_string()));
+        assert_eq!(san_to_uci(fen, "O-O-O"), Err("Illegal move: O-O-O".to_string()));
+    }
+
+    #[test]
+    fn test_san_pawn_captures_need_a_file() {
+        let fen = "4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1";
+        assert_eq!(san_to_uci(fen, "exd5"), Ok("e4d5".to_string()));
+        assert_eq!(san_to_uci(fen, "ed5"), Ok("e4d5".to_string()));
+        assert_eq!(san_to_uci(fen, "d5"), Err("Illegal move: d5".to_string()));
+        assert_eq!(san_to_uci(fen, "ee5"), Err("Illegal move: ee5".to_string()));
+
+        // With a pawn on d3 as well, "d4" is its push and still doesn't read as a capture.
+        let fen = "4k3/8/8/3n4/4P3/3P4/8/4K3 w - - 0 1";
+        assert_eq!(san_to_uci(fen, "d4"), Ok("d3d4".to_string()));
+        assert_eq!(san_to_uci(fen, "d5"), Err("Illegal move: d5".to_string()));
+        assert_eq!(san_to_uci(fen, "exd5"), Ok("e4d5".to_string()));
+        assert_eq!(san_to_uci(fen, "dxd4"), Err("Illegal move: dxd4".to_string()));
+    }
+
+    #[test]
+    fn test_san_disambiguation_and_check() {
+        let fen = "4k3/8/8/8/8/8/8/1N2KN1R w K - 0 1";
+        assert_eq!(san_to_uci(fen, "Nd2"), Err("Ambiguous move: Nd2".to_string()));
+        assert_eq!(san_to_uci(fen, "Nbd2"), Ok("b1d2".to_string()));
+        assert_eq!(san_to_uci(fen, "Nfd2"), Ok("f1d2".to_string()));
+        assert_eq!(san_to_uci(fen, "O-O"), Err("Illegal move: O-O".to_string()));
+
+        // The e2 knight is pinned, so Nd4 can only be the b3 knight.
+        let pinned = "4r1k1/8/8/8/8/1N6/4N3/4K3 w - - 0 1";
+        assert_eq!(san_to_uci(pinned, "Nd4"), Ok("b3d4".to_string()));
+        assert_eq!(san_to_uci(pinned, "N3d4"), Ok("b3d4".to_string()));
+        assert_eq!(san_to_uci("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "O-O"), Ok("e1g1".to_string()));
+    }
+}
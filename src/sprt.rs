@@ -0,0 +1,163 @@
+//! This file contains the statistics used to judge engine-vs-engine matches: the Elo difference
+//! implied by a match score, its error margin, and the log-likelihood ratio of a sequential
+//! probability ratio test (SPRT) between two Elo hypotheses.
+//!
+//! Elo differences use the logistic model, where a player rated `elo` points higher is expected to
+//! score 1 / (1 + 10^(-elo / 400)). The LLR uses the normal approximation to the trinomial
+//! (win/draw/loss) distribution, as used by fishtest and cutechess-cli.
+
+
+/// The z-score of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+
+/// The outcome of an SPRT, given the current log-likelihood ratio.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SprtResult {
+    /// H1 is accepted: the engine is at least elo1 stronger.
+    AcceptH1,
+    /// H0 is accepted: the engine is at most elo0 stronger.
+    AcceptH0,
+    /// More games are needed.
+    Continue,
+}
+
+
+/// The win, draw and loss counts of one engine against another.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    /// Make a MatchScore with the given counts.
+    pub fn new(wins: u32, draws: u32, losses: u32) -> Self {
+        MatchScore { wins, draws, losses }
+    }
+
+    /// Get the number of games played.
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Get the mean score per game, counting a draw as half a point. Returns None if no games have
+    /// been played.
+    pub fn score(&self) -> Option<f64> {
+        if self.games() == 0 { return None }
+        Some((self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64)
+    }
+
+    /// Get the variance of the score of a single game.
+    fn variance(&self) -> Option<f64> {
+        let score = self.score()?;
+        let n = self.games() as f64;
+        let (w, d, l) = (self.wins as f64 / n, self.draws as f64 / n, self.losses as f64 / n);
+        Some(w * (1.0 - score).powi(2) + d * (0.5 - score).powi(2) + l * score.powi(2))
+    }
+
+    /// Get the Elo difference implied by this score, along with the margin of its 95% confidence
+    /// interval. Returns None if no games have been played or if every game had the same result,
+    /// in which case the difference is unbounded. The margin is infinite if the interval reaches a
+    /// score of 0 or 1, as happens after a few games which were nearly all won or all lost.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let score = self.score()?;
+        let stddev = (self.variance()? / self.games() as f64).sqrt();
+        if stddev == 0.0 { return None }
+        let low = score_to_elo((score - Z_95 * stddev).max(0.0));
+        let high = score_to_elo((score + Z_95 * stddev).min(1.0));
+        Some((score_to_elo(score), (high - low) / 2.0))
+    }
+
+    /// Get the log-likelihood ratio of H1 (the Elo difference is elo1) against H0 (the Elo
+    /// difference is elo0). Returns 0 until there is enough variation in the results to estimate
+    /// the variance.
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let (Some(score), Some(variance)) = (self.score(), self.variance()) else { return 0.0 };
+        if variance == 0.0 { return 0.0 }
+        let (s0, s1) = (elo_to_score(elo0), elo_to_score(elo1));
+        (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance / self.games() as f64)
+    }
+
+    /// Run an SPRT of H0 (elo0) against H1 (elo1) on this score, with false positive rate alpha
+    /// and false negative rate beta.
+    pub fn sprt(&self, elo0: f64, elo1: f64, alpha: f64, beta: f64) -> SprtResult {
+        let (lower, upper) = sprt_bounds(alpha, beta);
+        let llr = self.llr(elo0, elo1);
+        if llr >= upper {
+            SprtResult::AcceptH1
+        } else if llr <= lower {
+            SprtResult::AcceptH0
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+
+/// Get the lower and upper LLR bounds of an SPRT with false positive rate alpha and false negative
+/// rate beta.
+pub fn sprt_bounds(alpha: f64, beta: f64) -> (f64, f64) {
+    ((beta / (1.0 - alpha)).ln(), ((1.0 - beta) / alpha).ln())
+}
+
+/// Get the expected score of a player rated elo points higher than their opponent.
+pub fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Get the Elo difference implied by the given expected score.
+pub fn score_to_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 0.01, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_elo() {
+        assert_close(score_to_elo(0.5), 0.0);
+        assert_close(score_to_elo(elo_to_score(150.0)), 150.0);
+        assert_eq!(MatchScore::default().elo(), None);
+        assert_eq!(MatchScore::new(0, 10, 0).elo(), None);
+
+        let (elo, margin) = MatchScore::new(60, 20, 20).elo().unwrap();
+        assert_close(elo, 147.19);
+        assert!(margin > 50.0 && margin < 100.0);
+        let (elo, _) = MatchScore::new(20, 20, 60).elo().unwrap();
+        assert_close(elo, -147.19);
+
+        // The upper bound of the interval is past a score of 1.
+        let (elo, margin) = MatchScore::new(9, 1, 0).elo().unwrap();
+        assert_close(elo, 511.50);
+        assert_eq!(margin, f64::INFINITY);
+        let (elo, margin) = MatchScore::new(0, 1, 9).elo().unwrap();
+        assert_close(elo, -511.50);
+        assert_eq!(margin, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_sprt() {
+        let (lower, upper) = sprt_bounds(0.05, 0.05);
+        assert_close(lower, -2.94);
+        assert_close(upper, 2.94);
+
+        assert_eq!(MatchScore::default().llr(0.0, 5.0), 0.0);
+        assert_eq!(MatchScore::new(1, 1, 1).sprt(0.0, 5.0, 0.05, 0.05), SprtResult::Continue);
+        assert_eq!(
+            MatchScore::new(6000, 8000, 5000).sprt(0.0, 5.0, 0.05, 0.05),
+            SprtResult::AcceptH1,
+        );
+        assert_eq!(
+            MatchScore::new(5000, 8000, 6000).sprt(0.0, 5.0, 0.05, 0.05),
+            SprtResult::AcceptH0,
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! This file contains the Square, File and Rank types, along with conversions to and from their
+//! algebraic names (e.g. "e4", "e", "4").
+use std::fmt;
+use std::str::FromStr;
+use crate::game_state::Color;
+
+
+/// Index for a square, from 0 (a1) to 63 (h8).
+/// See Little-Endian Rank-File Mapping @
+/// www.chessprogramming.org/Square_Mapping_Considerations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Square(u8);
+
+/// Index for a file, from 0 (a-file) to 7 (h-file).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct File(u8);
+
+/// Index for a rank, from 0 (1st rank) to 7 (8th rank).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rank(u8);
+
+
+const LSB3_BITMASK: u8 = 7;
+
+
+impl Square {
+    /// Make a Square from its index. Panics if the index is not in 0..64.
+    pub const fn new(idx: u8) -> Self {
+        assert!(idx < 64, "Invalid square index.");
+        Square(idx)
+    }
+
+    /// Make a Square from its file and rank.
+    pub const fn from_file_rank(file: File, rank: Rank) -> Self {
+        Square((rank.0 << 3) + file.0)
+    }
+
+    /// Get the index of this square, for indexing into per-square arrays.
+    pub const fn idx(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Get the index of this square as a u8.
+    pub const fn as_u8(self) -> u8 {
+        self.0
+    }
+
+    /// Get the file of this square.
+    pub const fn file(self) -> File {
+        File(self.0 & LSB3_BITMASK)
+    }
+
+    /// Get the rank of this square.
+    pub const fn rank(self) -> Rank {
+        Rank(self.0 >> 3)
+    }
+
+    /// Iterate over all squares from a1 to h8.
+    pub fn all() -> impl DoubleEndedIterator<Item = Square> {
+        (0..64).map(Square)
+    }
+
+    /// Get the square mirrored across the horizontal center line of the board, e.g. e2 -> e7.
+    pub const fn flip_vertical(self) -> Self {
+        Square(self.0 ^ 56)
+    }
+
+    /// Get the square mirrored across the vertical center line of the board, e.g. e2 -> d2.
+    pub const fn flip_horizontal(self) -> Self {
+        Square(self.0 ^ 7)
+    }
+
+    /// Get this square from the given color's point of view; i.e. flipped vertically for black.
+    pub const fn relative(self, color: Color) -> Self {
+        match color {
+            Color::White => self,
+            Color::Black => self.flip_vertical(),
+        }
+    }
+
+    /// Get the square offset by the given number of files and ranks, or None if it is off the
+    /// board.
+    pub fn offset(self, file_delta: i8, rank_delta: i8) -> Option<Self> {
+        let file = self.file().offset(file_delta)?;
+        let rank = self.rank().offset(rank_delta)?;
+        Some(Square::from_file_rank(file, rank))
+    }
+
+    /// Get the square one rank ahead of this one from the given color's point of view, or None if
+    /// it is off the board.
+    pub fn forward(self, color: Color) -> Option<Self> {
+        self.offset(0, color.forward())
+    }
+
+    /// Get the square one rank behind this one from the given color's point of view, or None if
+    /// it is off the board.
+    pub fn backward(self, color: Color) -> Option<Self> {
+        self.offset(0, -color.forward())
+    }
+
+    /// Get the Chebyshev (king move) distance between two squares.
+    pub fn chebyshev_distance(self, other: Square) -> u8 {
+        self.file().distance(other.file()).max(self.rank().distance(other.rank()))
+    }
+
+    /// Get the Manhattan (rook move without turning back) distance between two squares.
+    pub fn manhattan_distance(self, other: Square) -> u8 {
+        self.file().distance(other.file()) + self.rank().distance(other.rank())
+    }
+}
+
+// Named squares.
+impl Square {
+    pub const A1: Square = Square(0);
+    pub const B1: Square = Square(1);
+    pub const C1: Square = Square(2);
+    pub const D1: Square = Square(3);
+    pub const E1: Square = Square(4);
+    pub const F1: Square = Square(5);
+    pub const G1: Square = Square(6);
+    pub const H1: Square = Square(7);
+    pub const A2: Square = Square(8);
+    pub const B2: Square = Square(9);
+    pub const C2: Square = Square(10);
+    pub const D2: Square = Square(11);
+    pub const E2: Square = Square(12);
+    pub const F2: Square = Square(13);
+    pub const G2: Square = Square(14);
+    pub const H2: Square = Square(15);
+    pub const A3: Square = Square(16);
+    pub const B3: Square = Square(17);
+    pub const C3: Square = Square(18);
+    pub const D3: Square = Square(19);
+    pub const E3: Square = Square(20);
+    pub const F3: Square = Square(21);
+    pub const G3: Square = Square(22);
+    pub const H3: Square = Square(23);
+    pub const A4: Square = Square(24);
+    pub const B4: Square = Square(25);
+    pub const C4: Square = Square(26);
+    pub const D4: Square = Square(27);
+    pub const E4: Square = Square(28);
+    pub const F4: Square = Square(29);
+    pub const G4: Square = Square(30);
+    pub const H4: Square = Square(31);
+    pub const A5: Square = Square(32);
+    pub const B5: Square = Square(33);
+    pub const C5: Square = Square(34);
+    pub const D5: Square = Square(35);
+    pub const E5: Square = Square(36);
+    pub const F5: Square = Square(37);
+    pub const G5: Square = Square(38);
+    pub const H5: Square = Square(39);
+    pub const A6: Square = Square(40);
+    pub const B6: Square = Square(41);
+    pub const C6: Square = Square(42);
+    pub const D6: Square = Square(43);
+    pub const E6: Square = Square(44);
+    pub const F6: Square = Square(45);
+    pub const G6: Square = Square(46);
+    pub const H6: Square = Square(47);
+    pub const A7: Square = Square(48);
+    pub const B7: Square = Square(49);
+    pub const C7: Square = Square(50);
+    pub const D7: Square = Square(51);
+    pub const E7: Square = Square(52);
+    pub const F7: Square = Square(53);
+    pub const G7: Square = Square(54);
+    pub const H7: Square = Square(55);
+    pub const A8: Square = Square(56);
+    pub const B8: Square = Square(57);
+    pub const C8: Square = Square(58);
+    pub const D8: Square = Square(59);
+    pub const E8: Square = Square(60);
+    pub const F8: Square = Square(61);
+    pub const G8: Square = Square(62);
+    pub const H8: Square = Square(63);
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+
+impl FromStr for Square {
+    type Err = String;
+
+    /// Parse a square from its algebraic name, e.g. "e4".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() || s.len() != 2 {
+            return Err(format!("Invalid square: {}", s));
+        }
+        let file: File = s[0..1].parse()?;
+        let rank: Rank = s[1..2].parse()?;
+        Ok(Square::from_file_rank(file, rank))
+    }
+}
+
+
+impl File {
+    pub const A: File = File(0);
+    pub const B: File = File(1);
+    pub const C: File = File(2);
+    pub const D: File = File(3);
+    pub const E: File = File(4);
+    pub const F: File = File(5);
+    pub const G: File = File(6);
+    pub const H: File = File(7);
+
+    /// Make a File from its index. Panics if the index is not in 0..8.
+    pub const fn new(idx: u8) -> Self {
+        assert!(idx < 8, "Invalid file index.");
+        File(idx)
+    }
+
+    /// Get the index of this file.
+    pub const fn idx(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Iterate over all files from a to h.
+    pub fn all() -> impl DoubleEndedIterator<Item = File> {
+        (0..8).map(File)
+    }
+
+    /// Get the file mirrored across the vertical center line of the board, e.g. b -> g.
+    pub const fn flip(self) -> Self {
+        File(7 - self.0)
+    }
+
+    /// Get the file offset by the given number of files, or None if it is off the board.
+    pub fn offset(self, delta: i8) -> Option<Self> {
+        let idx = (self.0 as i8).checked_add(delta)?;
+        if (0..8).contains(&idx) { Some(File(idx as u8)) } else { None }
+    }
+
+    /// Get the number of files between this file and another.
+    pub fn distance(self, other: File) -> u8 {
+        self.0.abs_diff(other.0)
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", (b'a' + self.0) as char)
+    }
+}
+
+impl FromStr for File {
+    type Err = String;
+
+    /// Parse a file from its algebraic name, e.g. "e".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            [c @ b'a'..=b'h'] => Ok(File(c - b'a')),
+            _ => Err(format!("Invalid file: {}", s)),
+        }
+    }
+}
+
+
+impl Rank {
+    pub const R1: Rank = Rank(0);
+    pub const R2: Rank = Rank(1);
+    pub const R3: Rank = Rank(2);
+    pub const R4: Rank = Rank(3);
+    pub const R5: Rank = Rank(4);
+    pub const R6: Rank = Rank(5);
+    pub const R7: Rank = Rank(6);
+    pub const R8: Rank = Rank(7);
+
+    /// Make a Rank from its index. Panics if the index is not in 0..8.
+    pub const fn new(idx: u8) -> Self {
+        assert!(idx < 8, "Invalid rank index.");
+        Rank(idx)
+    }
+
+    /// Get the index of this rank.
+    pub const fn idx(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Iterate over all ranks from 1 to 8.
+    pub fn all() -> impl DoubleEndedIterator<Item = Rank> {
+        (0..8).map(Rank)
+    }
+
+    /// Get the rank mirrored across the horizontal center line of the board, e.g. 2 -> 7.
+    pub const fn flip(self) -> Self {
+        Rank(7 - self.0)
+    }
+
+    /// Get this rank from the given color's point of view; i.e. flipped for black.
+    pub const fn relative(self, color: Color) -> Self {
+        match color {
+            Color::White => self,
+            Color::Black => self.flip(),
+        }
+    }
+
+    /// Get the rank offset by the given number of ranks, or None if it is off the board.
+    pub fn offset(self, delta: i8) -> Option<Self> {
+        let idx = (self.0 as i8).checked_add(delta)?;
+        if (0..8).contains(&idx) { Some(Rank(idx as u8)) } else { None }
+    }
+
+    /// Get the number of ranks between this rank and another.
+    pub fn distance(self, other: Rank) -> u8 {
+        self.0.abs_diff(other.0)
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", (b'1' + self.0) as char)
+    }
+}
+
+impl FromStr for Rank {
+    type Err = String;
+
+    /// Parse a rank from its algebraic name, e.g. "4".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            [c @ b'1'..=b'8'] => Ok(Rank(c - b'1')),
+            _ => Err(format!("Invalid rank: {}", s)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_algebraic_round_trip() {
+        for sq in Square::all() {
+            assert_eq!(sq.to_string().parse::<Square>().unwrap(), sq);
+        }
+        assert_eq!("a1".parse::<Square>().unwrap(), Square::A1);
+        assert_eq!("e4".parse::<Square>().unwrap(), Square::E4);
+        assert_eq!("h8".parse::<Square>().unwrap(), Square::H8);
+        assert_eq!(Square::E4.to_string(), "e4");
+        assert!("i1".parse::<Square>().is_err());
+        assert!("a9".parse::<Square>().is_err());
+        assert!("e".parse::<Square>().is_err());
+        assert!("e44".parse::<Square>().is_err());
+    }
+
+    #[test]
+    fn test_file_rank() {
+        assert_eq!(Square::E4.file(), File::E);
+        assert_eq!(Square::E4.rank(), Rank::R4);
+        assert_eq!(Square::from_file_rank(File::H, Rank::R1), Square::H1);
+        assert_eq!(Rank::R2.relative(Color::Black), Rank::R7);
+        assert_eq!(File::B.flip(), File::G);
+    }
+
+    #[test]
+    fn test_flips() {
+        assert_eq!(Square::E2.flip_vertical(), Square::E7);
+        assert_eq!(Square::E2.flip_horizontal(), Square::D2);
+        assert_eq!(Square::A1.relative(Color::White), Square::A1);
+        assert_eq!(Square::A1.relative(Color::Black), Square::A8);
+    }
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(Square::E4.offset(1, 2), Some(Square::F6));
+        assert_eq!(Square::E4.offset(-4, -3), Some(Square::A1));
+        assert_eq!(Square::A1.offset(-1, 0), None);
+        assert_eq!(Square::H4.offset(1, 0), None);
+        assert_eq!(Square::E8.offset(0, 1), None);
+        assert_eq!(Square::E2.forward(Color::White), Some(Square::E3));
+        assert_eq!(Square::E7.forward(Color::Black), Some(Square::E6));
+        assert_eq!(Square::E1.backward(Color::White), None);
+
+        // Deltas which would overflow an i8 are off the board rather than a panic.
+        assert_eq!(Square::H8.offset(i8::MAX, i8::MAX), None);
+        assert_eq!(File::H.offset(i8::MAX), None);
+        assert_eq!(Rank::R1.offset(i8::MIN), None);
+    }
+
+    #[test]
+    fn test_distances() {
+        assert_eq!(Square::A1.chebyshev_distance(Square::H8), 7);
+        assert_eq!(Square::A1.manhattan_distance(Square::H8), 14);
+        assert_eq!(Square::E4.chebyshev_distance(Square::F6), 2);
+        assert_eq!(Square::E4.manhattan_distance(Square::F6), 3);
+        assert_eq!(Square::D5.chebyshev_distance(Square::D5), 0);
+    }
+}
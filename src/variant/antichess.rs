@@ -0,0 +1,56 @@
+//! This file contains the Antichess variant, in which each side tries to lose all of its pieces.
+//! Kings are ordinary pieces with no royal power and castling is not allowed.
+//!
+//! TODO: captures are compulsory and a side with no legal moves wins. Both need legal move
+//! generation, so only losing all pieces is detected for now.
+use crate::fen;
+use crate::game_state::{ CastleRights, Color, GameState };
+use crate::variant::{ Outcome, Variant };
+
+
+/// Antichess.
+pub struct Antichess;
+
+impl Variant for Antichess {
+    fn name(&self) -> &'static str {
+        "antichess"
+    }
+
+    fn outcome(&self, state: &GameState) -> Option<Outcome> {
+        Color::all()
+            .find(|&color| state.side_pieces(color).is_empty())
+            .map(Outcome::Win)
+    }
+
+    fn validate(&self, state: &GameState) -> Result<(), String> {
+        state.validate_board()
+    }
+
+    fn parse_fen(&self, fen: &str) -> Result<GameState, String> {
+        let mut state = fen::try_parse_fen(fen)?;
+        state.set_castlerights(CastleRights::NONE);
+        Ok(state)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_move::{ GameMove, MoveType };
+    use crate::game_state::Game;
+    use crate::square::Square;
+
+    #[test]
+    fn test_losing_all_pieces_wins() {
+        let state = Antichess.parse_fen("8/8/8/8/8/8/1k6/K7 w KQkq - 0 1").unwrap();
+        assert_eq!(state.castlerights, CastleRights::NONE);
+
+        let mut game = Game::with_variant(state, Box::new(Antichess));
+        assert_eq!(game.outcome(), None);
+        game.make(GameMove::new(Square::A1, Square::B2, MoveType::Capture));
+        assert_eq!(game.outcome(), Some(Outcome::Win(Color::Black)));
+        game.unmake().unwrap();
+        assert_eq!(game.outcome(), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! This file contains the Atomic variant, in which every capture causes an explosion that removes
+//! the capturing piece and every piece other than a pawn next to the capture square. A side wins
+//! when the other side's king is exploded.
+//!
+//! TODO: a move which explodes its own king is illegal, and kings may stand next to each other.
+//! Both need legal move generation.
+use crate::bits::bitboard::Bitboard;
+use crate::bits::masks;
+use crate::game_move::GameMove;
+use crate::game_state::{ Color, GameState, Piece, PieceType, UndoRecord };
+use crate::variant::{ Outcome, Variant, VariantUndo };
+
+
+/// Pieces removed by an explosion, in square order over the capture square and its neighbours.
+pub type Explosion = [Option<Piece>; 9];
+
+
+/// Atomic.
+pub struct Atomic;
+
+impl Variant for Atomic {
+    fn name(&self) -> &'static str {
+        "atomic"
+    }
+
+    fn make(&self, state: &mut GameState, game_move: GameMove) -> (UndoRecord, VariantUndo) {
+        let undo = state.make(game_move);
+        if !game_move.is_capture() { return (undo, VariantUndo::None) }
+
+        let tosquare = game_move.tosquare();
+        let mut explosion = [None; 9];
+        for (i, sq) in blast_squares(game_move).into_iter().enumerate() {
+            match state.occupying_piece(sq) {
+                Some(piece) if sq == tosquare || piece.kind() != PieceType::Pawn => {
+                    explosion[i] = state.remove_piece(sq);
+                    state.update_castlerights(sq, sq);
+                },
+                _ => {},
+            }
+        }
+        (undo, VariantUndo::Explosion(explosion))
+    }
+
+    fn unmake(&self, state: &mut GameState, undo: UndoRecord, variant_undo: VariantUndo) {
+        if let VariantUndo::Explosion(explosion) = variant_undo {
+            for (i, sq) in blast_squares(undo.game_move).into_iter().enumerate() {
+                if let Some(piece) = explosion[i] {
+                    state.add_piece(piece, sq);
+                }
+            }
+        }
+        state.unmake(undo)
+    }
+
+    fn outcome(&self, state: &GameState) -> Option<Outcome> {
+        Color::all()
+            .find(|&color| state.pieces(color, PieceType::King).is_empty())
+            .map(|color| Outcome::Win(!color))
+    }
+
+    fn validate(&self, state: &GameState) -> Result<(), String> {
+        state.validate_board()?;
+        for color in Color::all() {
+            let kings = state.pieces(color, PieceType::King).popcount();
+            if kings > 1 {
+                return Err(format!("Side {} has {} kings.", color, kings));
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Get the squares hit by the explosion of the given capture: the capture square and its
+/// neighbours.
+fn blast_squares(game_move: GameMove) -> Bitboard {
+    let tosquare = game_move.tosquare();
+    masks::KING_MOVES[tosquare.idx()] | Bitboard::from_square(tosquare)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_move::MoveType;
+    use crate::game_state::Game;
+    use crate::square::Square;
+
+    /// Start a game of Atomic from the given FEN string.
+    fn atomic_game(fen: &str) -> Game {
+        Game::with_variant(Atomic.parse_fen(fen).unwrap(), Box::new(Atomic))
+    }
+
+    /// Get the FEN string of the game's current position.
+    fn current_fen(game: &Game) -> String {
+        game.variant().to_fen(&game.current_state())
+    }
+
+    #[test]
+    fn test_capture_explodes_neighbours() {
+        let fen = "4k3/8/8/2pR4/3pP3/2n2N2/8/4K3 w - - 0 1";
+        let mut game = atomic_game(fen);
+
+        // The capturing knight, the c3 knight and the d5 rook explode; the pawns next to d4 don't.
+        game.make(GameMove::new(Square::F3, Square::D4, MoveType::Capture));
+        assert_eq!(current_fen(&game), "4k3/8/8/2p5/4P3/8/8/4K3 b - - 0 1");
+        assert_eq!(game.outcome(), None);
+        game.unmake().unwrap();
+        assert_eq!(current_fen(&game), fen);
+    }
+
+    #[test]
+    fn test_explosions_clear_castle_rights() {
+        let fen = "r3k1nr/8/8/8/8/8/8/R3K1R1 w Qkq - 0 1";
+        let mut game = atomic_game(fen);
+        game.make(GameMove::new(Square::G1, Square::G8, MoveType::Capture));
+        assert_eq!(current_fen(&game), "r3k3/8/8/8/8/8/8/R3K3 b Qq - 0 1");
+        game.unmake().unwrap();
+        assert_eq!(current_fen(&game), fen);
+    }
+
+    #[test]
+    fn test_explosions_follow_their_own_position() {
+        // Two copies of a position each make a different capture, then are unmade out of order.
+        let fen = "4k3/8/8/2pR4/3pP3/2n2N2/8/4K3 w - - 0 1";
+        let start = Atomic.parse_fen(fen).unwrap();
+        let (mut a, mut b) = (start, start);
+        let (undo_a, explosion_a) =
+            Atomic.make(&mut a, GameMove::new(Square::F3, Square::D4, MoveType::Capture));
+        let (undo_b, explosion_b) =
+            Atomic.make(&mut b, GameMove::new(Square::D5, Square::C5, MoveType::Capture));
+        Atomic.unmake(&mut a, undo_a, explosion_a);
+        Atomic.unmake(&mut b, undo_b, explosion_b);
+        assert_eq!(Atomic.to_fen(&a), fen);
+        assert_eq!(Atomic.to_fen(&b), fen);
+    }
+
+    #[test]
+    fn test_exploding_king_wins() {
+        let fen = "3rk3/8/8/8/8/8/8/3RK3 w - - 0 1";
+        let mut game = atomic_game(fen);
+        game.make(GameMove::new(Square::D1, Square::D8, MoveType::Capture));
+        assert_eq!(current_fen(&game), "8/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(game.outcome(), Some(Outcome::Win(Color::White)));
+        game.unmake().unwrap();
+        assert_eq!(game.outcome(), None);
+    }
+}
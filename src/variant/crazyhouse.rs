@@ -0,0 +1,217 @@
+//! This file contains the Crazyhouse variant, in which captured pieces go to the capturer's
+//! pocket and can be dropped back onto the board as a move. A captured promoted piece goes to the
+//! pocket as a pawn.
+//!
+//! FEN strings for Crazyhouse give the pockets in brackets after the board, e.g. "[Qn]" for a
+//! white queen and a black knight in hand, and mark promoted pieces with a '~', e.g. "Q~".
+use crate::bits::bitboard::Bitboard;
+use crate::fen;
+use crate::game_move::GameMove;
+use crate::game_state::{ Color, GameState, Piece, PieceType, UndoRecord };
+use crate::square::{ Square, File, Rank };
+use crate::variant::{ Variant, VariantUndo };
+
+
+/// The order in which pocket pieces are written in FEN, for each side.
+const POCKET_FEN_ORDER: [PieceType; 5] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+
+/// Crazyhouse.
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "crazyhouse"
+    }
+
+    fn has_drops(&self) -> bool {
+        true
+    }
+
+    fn make(&self, state: &mut GameState, game_move: GameMove) -> (UndoRecord, VariantUndo) {
+        let undo = state.make(game_move);
+
+        // Add the captured piece to the mover's pocket, demoting it if it was promoted.
+        if let Some(kind) = pocket_kind(&undo) {
+            state.pockets[(!state.side_to_move).idx()][kind as usize] += 1;
+        }
+        (undo, VariantUndo::None)
+    }
+
+    fn unmake(&self, state: &mut GameState, undo: UndoRecord, _variant_undo: VariantUndo) {
+        if let Some(kind) = pocket_kind(&undo) {
+            state.pockets[(!state.side_to_move).idx()][kind as usize] -= 1;
+        }
+        state.unmake(undo)
+    }
+
+    fn parse_fen(&self, fen: &str) -> Result<GameState, String> {
+        let mut fields: Vec<String> = fen.split(" ").map(String::from).collect();
+        let (board, pocket_str) = match fields[0].split_once('[') {
+            Some((board, pocket_str)) => {
+                let pocket_str = pocket_str.strip_suffix(']')
+                    .ok_or_else(|| format!("Invalid pocket: [{}", pocket_str))?;
+                (board.to_string(), pocket_str.to_string())
+            },
+            None => (fields[0].clone(), String::new()),
+        };
+
+        // Find the promoted pieces, then parse the board without the markers.
+        let mut promoted = Bitboard::EMPTY;
+        for (rank, rank_str) in board_ranks(&board) {
+            for_each_rank_char(rank_str, rank, |c, sq| {
+                if c == '~' { promoted |= Bitboard::from_square(sq) }
+            })?;
+        }
+        fields[0] = board.replace('~', "");
+
+        let mut state = fen::try_parse_fen(&fields.join(" "))?;
+        state.promoted = promoted;
+        for c in pocket_str.chars() {
+            let piece = fen::piece_from_char(c)
+                .filter(|piece| piece.kind() != PieceType::King)
+                .ok_or_else(|| format!("Invalid pocket piece: {}", c))?;
+            state.pockets[piece.color().idx()][piece.kind() as usize] += 1;
+        }
+        Ok(state)
+    }
+
+    fn to_fen(&self, state: &GameState) -> String {
+        let fen = fen::to_fen(state);
+        let (board, rest) = fen.split_once(' ').unwrap();
+
+        let mut result = String::new();
+        for (rank, rank_str) in board_ranks(board) {
+            if rank != Rank::R8 { result.push('/') }
+            for_each_rank_char(rank_str, rank, |c, sq| {
+                result.push(c);
+                if c.is_ascii_alphabetic() && state.promoted.contains(sq) { result.push('~') }
+            }).expect("The standard FEN board has no promotion markers.");
+        }
+
+        result.push('[');
+        for color in Color::all() {
+            for kind in POCKET_FEN_ORDER {
+                let c = fen::char_from_piece(Piece::new(color, kind)).unwrap();
+                for _ in 0..state.pockets[color.idx()][kind as usize] {
+                    result.push(c);
+                }
+            }
+        }
+        result.push(']');
+
+        format!("{} {}", result, rest)
+    }
+}
+
+
+/// Get the kind of piece the capture recorded in the UndoRecord puts in the capturer's pocket, if
+/// the move was a capture.
+fn pocket_kind(undo: &UndoRecord) -> Option<PieceType> {
+    let captured = undo.captured?;
+    Some(if undo.captured_promoted { PieceType::Pawn } else { captured.kind() })
+}
+
+/// Split the FEN board field into its ranks, from the 8th rank down.
+fn board_ranks(board: &str) -> impl Iterator<Item = (Rank, &str)> {
+    Rank::all().rev().zip(board.split('/'))
+}
+
+/// Call f with each character of a rank of the FEN board field, along with the square it
+/// describes. For digits this is the first empty square; for '~' it is the square of the piece
+/// before it, so a '~' which doesn't follow a piece is an error.
+fn for_each_rank_char<F>(rank_str: &str, rank: Rank, mut f: F) -> Result<(), String>
+where F: FnMut(char, Square) {
+    let mut file_idx: u8 = 0;
+    let mut prev = None;
+    for c in rank_str.chars() {
+        let sq_file = match c {
+            '~' if prev.is_some_and(|p: char| p.is_ascii_alphabetic()) => file_idx - 1,
+            '~' => return Err(format!("Promotion marker without a piece: {}", rank_str)),
+            _ => file_idx,
+        };
+        f(c, Square::from_file_rank(File::new(sq_file.min(7)), rank));
+        match c.to_digit(10) {
+            Some(count) => file_idx = file_idx.saturating_add(count as u8),
+            None if c != '~' => file_idx = file_idx.saturating_add(1),
+            None => {},
+        }
+        prev = Some(c);
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_move::MoveType;
+    use crate::game_state::Game;
+
+    #[test]
+    fn test_pocket_fen() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1";
+        let state = Crazyhouse.parse_fen(fen).unwrap();
+        assert_eq!(state.pockets, [[0; 5]; 2]);
+        assert_eq!(Crazyhouse.to_fen(&state), fen);
+        let no_pocket = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(Crazyhouse.parse_fen(no_pocket).unwrap().pockets, [[0; 5]; 2]);
+
+        let fen = "r1b1kQ~1r/8/8/8/8/8/8/R3K2R[QNPPbn] b KQkq - 7 4";
+        let state = Crazyhouse.parse_fen(fen).unwrap();
+        assert_eq!(state.pockets[Color::White.idx()], [2, 0, 1, 0, 1]);
+        assert_eq!(state.pockets[Color::Black.idx()], [0, 1, 1, 0, 0]);
+        assert_eq!(state.promoted, Bitboard::from_square(Square::F8));
+        assert_eq!(state.occupying_piece(Square::F8), Some(Piece::WhiteQueen));
+        assert_eq!(Crazyhouse.to_fen(&state), fen);
+    }
+
+    #[test]
+    fn test_malformed_crazyhouse_fen() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3[Q w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3[Qx] w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3[K] w - - 0 1",
+            "~4k3/8/8/8/8/8/8/4K3[] w - - 0 1",
+            "4k3/8/8/8/8/8/8/1~3K3[] w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K4[] w - - 0 1",
+        ] {
+            assert!(Crazyhouse.parse_fen(fen).is_err(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_captures_fill_pocket_and_promoted_pieces_demote() {
+        let fen = "4k3/1P6/8/8/8/3K4/8/7r[n] w - - 0 1";
+        let mut game = Game::with_variant(Crazyhouse.parse_fen(fen).unwrap(), Box::new(Crazyhouse));
+        let current_fen = |game: &Game| Crazyhouse.to_fen(&game.current_state());
+
+        game.make(GameMove::new(Square::B7, Square::B8, MoveType::QueenPromo));
+        assert_eq!(current_fen(&game), "1Q~2k3/8/8/8/8/3K4/8/7r[n] b - - 0 1");
+
+        game.make(GameMove::new(Square::E8, Square::E7, MoveType::Quiet));
+        game.make(GameMove::new(Square::B8, Square::B1, MoveType::Quiet));
+        assert_eq!(game.current_state().promoted, Bitboard::from_square(Square::B1));
+        game.make(GameMove::new(Square::H1, Square::B1, MoveType::Capture));
+        assert_eq!(current_fen(&game), "8/4k3/8/8/8/3K4/8/1r6[np] w - - 0 3");
+
+        game.make(GameMove::new(Square::D3, Square::E2, MoveType::Quiet));
+        game.make(GameMove::new_drop(PieceType::Knight, Square::D4));
+        assert_eq!(current_fen(&game), "8/4k3/8/8/3n4/8/4K3/1r6[p] w - - 2 4");
+
+        for _ in 0..3 {
+            game.unmake().unwrap();
+        }
+        assert_eq!(current_fen(&game), "8/4k3/8/8/8/3K4/8/1Q~5r[n] b - - 2 2");
+        for _ in 0..3 {
+            game.unmake().unwrap();
+        }
+        assert_eq!(current_fen(&game), fen);
+    }
+}
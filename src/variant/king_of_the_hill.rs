@@ -0,0 +1,48 @@
+//! This file contains the King of the Hill variant, in which a side also wins by moving its king to
+//! one of the four center squares.
+use crate::bits::bitboard::Bitboard;
+use crate::game_state::{ Color, GameState, PieceType };
+use crate::variant::{ Outcome, Variant };
+
+
+/// The squares d4, e4, d5 and e5.
+const HILL: Bitboard = Bitboard(0x0000_0018_1800_0000);
+
+
+/// King of the Hill.
+pub struct KingOfTheHill;
+
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "kingofthehill"
+    }
+
+    fn outcome(&self, state: &GameState) -> Option<Outcome> {
+        Color::all()
+            .find(|&color| !(state.pieces(color, PieceType::King) & HILL).is_empty())
+            .map(Outcome::Win)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_move::{ GameMove, MoveType };
+    use crate::game_state::Game;
+    use crate::square::Square;
+
+    #[test]
+    fn test_king_on_hill_wins() {
+        let hill: Bitboard = [Square::D4, Square::E4, Square::D5, Square::E5].into_iter().collect();
+        assert_eq!(HILL, hill);
+
+        let state = KingOfTheHill.parse_fen("4k3/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+        let mut game = Game::with_variant(state, Box::new(KingOfTheHill));
+        assert_eq!(game.outcome(), None);
+        game.make(GameMove::new(Square::E3, Square::D4, MoveType::Quiet));
+        assert_eq!(game.outcome(), Some(Outcome::Win(Color::White)));
+        game.unmake().unwrap();
+        assert_eq!(game.outcome(), None);
+    }
+}
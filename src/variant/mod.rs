@@ -0,0 +1,139 @@
+//! This file contains the Variant trait, which lets the rules of chess be swapped out, and the
+//! standard rule set.
+//!
+//! All variants share the bitboard GameState and the GameMove encoding. A variant overrides the
+//! parts of the rules it changes: how moves are generated and applied, how the game can end, and
+//! how positions are read from and written to FEN.
+use crate::fen;
+use crate::game_move::GameMove;
+use crate::game_state::{ Color, GameState, UndoRecord };
+use crate::move_gen;
+use crate::move_list::MoveList;
+
+pub mod antichess;
+pub mod atomic;
+pub mod crazyhouse;
+pub mod king_of_the_hill;
+pub mod three_check;
+
+pub use antichess::Antichess;
+pub use atomic::Atomic;
+pub use crazyhouse::Crazyhouse;
+pub use king_of_the_hill::KingOfTheHill;
+pub use three_check::ThreeCheck;
+pub use atomic::Explosion;
+
+
+/// The names of the supported variants, as used by the UCI_Variant option.
+pub const VARIANT_NAMES: [&str; 6] =
+    ["chess", "kingofthehill", "3check", "antichess", "atomic", "crazyhouse"];
+
+
+/// The result of a finished game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Color),
+    Draw,
+}
+
+
+/// What a variant needs to undo its own rules for one move, beyond the GameState's UndoRecord. It
+/// is returned by `Variant::make`, kept next to the UndoRecord, and passed back to `unmake`, so the
+/// variant itself holds no per-game state.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum VariantUndo {
+    /// Nothing; the variant can work out what it needs from the board and the UndoRecord.
+    #[default]
+    None,
+    /// The pieces removed by an Atomic explosion.
+    Explosion(Explosion),
+}
+
+
+/// A set of rules for playing chess. The default implementations are the standard rules.
+pub trait Variant {
+    /// Get the name of this variant, as used by the UCI_Variant option.
+    fn name(&self) -> &'static str;
+
+    /// Generate the legal moves in the given position.
+    fn gen_legal_moves(&self, state: &GameState) -> MoveList {
+        move_gen::gen_legal_moves(*state)
+    }
+
+    /// Apply the given move to the GameState in-place. Returns an UndoRecord and the variant's own
+    /// undo data, which can be passed to `unmake` to revert the move.
+    fn make(&self, state: &mut GameState, game_move: GameMove) -> (UndoRecord, VariantUndo) {
+        (state.make(game_move), VariantUndo::None)
+    }
+
+    /// Revert the move recorded in the given UndoRecord and variant undo data, which must be the
+    /// last move applied to the GameState with `make`.
+    fn unmake(&self, state: &mut GameState, undo: UndoRecord, _variant_undo: VariantUndo) {
+        state.unmake(undo)
+    }
+
+    /// Returns whether pieces can be dropped onto the board from a pocket in this variant.
+    fn has_drops(&self) -> bool {
+        false
+    }
+
+    /// Get the outcome of the game if it has ended by a rule specific to this variant.
+    ///
+    /// TODO: checkmate and stalemate need legal move generation, so they are not detected yet.
+    fn outcome(&self, _state: &GameState) -> Option<Outcome> {
+        None
+    }
+
+    /// Check that the GameState is consistent and sane under this variant's rules.
+    fn validate(&self, state: &GameState) -> Result<(), String> {
+        state.validate()
+    }
+
+    /// Make a GameState from the given FEN string, or describe why it is malformed.
+    fn parse_fen(&self, fen: &str) -> Result<GameState, String> {
+        fen::try_parse_fen(fen)
+    }
+
+    /// Make a FEN string from the given GameState.
+    fn to_fen(&self, state: &GameState) -> String {
+        fen::to_fen(state)
+    }
+}
+
+
+/// Standard chess.
+pub struct Standard;
+
+impl Variant for Standard {
+    fn name(&self) -> &'static str {
+        "chess"
+    }
+}
+
+
+/// Get the variant with the given name, as given by the UCI_Variant option.
+pub fn from_name(name: &str) -> Result<Box<dyn Variant>, String> {
+    match name {
+        "chess" => Ok(Box::new(Standard)),
+        "kingofthehill" => Ok(Box::new(KingOfTheHill)),
+        "3check" => Ok(Box::new(ThreeCheck)),
+        "antichess" => Ok(Box::new(Antichess)),
+        "atomic" => Ok(Box::new(Atomic)),
+        "crazyhouse" => Ok(Box::new(Crazyhouse)),
+        _ => Err(format!("Unknown variant: {}", name)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        for name in VARIANT_NAMES {
+            assert_eq!(from_name(name).unwrap().name(), name);
+        }
+        assert!(from_name("crazyhouse960").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! This file contains the Three-check variant, in which a side also wins by giving check three
+//! times.
+//!
+//! FEN strings for Three-check have an extra field after the en-passant square giving the number
+//! of checks each side has left to give, e.g. "3+3" at the start of the game.
+use crate::fen;
+use crate::game_move::GameMove;
+use crate::game_state::{ Color, GameState, UndoRecord };
+use crate::move_gen;
+use crate::variant::{ Outcome, Variant, VariantUndo };
+
+
+/// The number of checks needed to win.
+const CHECKS_TO_WIN: u8 = 3;
+
+/// The index of the remaining checks field in a Three-check FEN string.
+const CHECKS_FIELD: usize = 4;
+
+
+/// Three-check.
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "3check"
+    }
+
+    fn make(&self, state: &mut GameState, game_move: GameMove) -> (UndoRecord, VariantUndo) {
+        let undo = state.make(game_move);
+        if move_gen::in_check(state, state.side_to_move) {
+            state.checks_given[(!state.side_to_move).idx()] += 1;
+        }
+        (undo, VariantUndo::None)
+    }
+
+    fn unmake(&self, state: &mut GameState, undo: UndoRecord, _variant_undo: VariantUndo) {
+        if move_gen::in_check(state, state.side_to_move) {
+            state.checks_given[(!state.side_to_move).idx()] -= 1;
+        }
+        state.unmake(undo)
+    }
+
+    fn outcome(&self, state: &GameState) -> Option<Outcome> {
+        Color::all()
+            .find(|color| state.checks_given[color.idx()] >= CHECKS_TO_WIN)
+            .map(Outcome::Win)
+    }
+
+    fn parse_fen(&self, fen: &str) -> Result<GameState, String> {
+        let mut fields: Vec<&str> = fen.split(' ').collect();
+        if fields.len() != 7 { return Err(format!("Invalid Three-check FEN string: {}", fen)) }
+        let checks_str = fields.remove(CHECKS_FIELD);
+        let invalid = || format!("Invalid remaining checks: {}", checks_str);
+
+        let mut state = fen::try_parse_fen(&fields.join(" "))?;
+        let (white, black) = checks_str.split_once('+').ok_or_else(invalid)?;
+        for (color, count_str) in Color::all().zip([white, black]) {
+            let count: u8 = count_str.parse().map_err(|_| invalid())?;
+            if count > CHECKS_TO_WIN { return Err(invalid()) }
+            state.checks_given[color.idx()] = CHECKS_TO_WIN - count;
+        }
+        Ok(state)
+    }
+
+    fn to_fen(&self, state: &GameState) -> String {
+        let fen = fen::to_fen(state);
+        let mut fields: Vec<&str> = fen.split(" ").collect();
+        let checks_str = format!(
+            "{}+{}",
+            CHECKS_TO_WIN - state.checks_given[Color::White.idx()],
+            CHECKS_TO_WIN - state.checks_given[Color::Black.idx()],
+        );
+        fields.insert(CHECKS_FIELD, &checks_str);
+        fields.join(" ")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_move::MoveType;
+    use crate::game_state::Game;
+    use crate::square::Square;
+
+    #[test]
+    fn test_checks_are_counted() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 2+3 0 1";
+        let state = ThreeCheck.parse_fen(fen).unwrap();
+        assert_eq!(state.checks_given, [1, 0]);
+        assert_eq!(ThreeCheck.to_fen(&state), fen);
+
+        let mut game = Game::with_variant(state, Box::new(ThreeCheck));
+        game.make(GameMove::new(Square::A1, Square::A8, MoveType::Quiet));
+        assert_eq!(ThreeCheck.to_fen(&game.current_state()), "R3k3/8/8/8/8/8/8/4K3 b - - 1+3 1 1");
+        assert_eq!(game.outcome(), None);
+
+        game.make(GameMove::new(Square::E8, Square::E7, MoveType::Quiet));
+        game.make(GameMove::new(Square::A8, Square::A7, MoveType::Quiet));
+        assert_eq!(game.current_state().checks_given, [3, 0]);
+        assert_eq!(game.outcome(), Some(Outcome::Win(Color::White)));
+
+        game.unmake().unwrap();
+        game.unmake().unwrap();
+        game.unmake().unwrap();
+        assert_eq!(ThreeCheck.to_fen(&game.current_state()), fen);
+    }
+
+    #[test]
+    fn test_malformed_remaining_checks() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w - - 3 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w - - 3+x 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w - - 3+4 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w - - 3+3+3 0 1",
+        ] {
+            assert!(ThreeCheck.parse_fen(fen).is_err(), "{}", fen);
+        }
+    }
+}
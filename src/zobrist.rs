@@ -0,0 +1,82 @@
+//! This file contains the Zobrist keys GameState uses to hash positions.
+//!
+//! These are the engine's own keys, separate from the Polyglot ones in `polyglot`, so the hash
+//! can be kept up to date cheaply: the en-passant file is hashed whenever there is an ep square,
+//! rather than only when a pawn could capture on it.
+use crate::game_state::{ CastleRights, Color, GameState, Piece, PieceType };
+use crate::square::{ File, Square };
+
+
+/// Offsets of each part of the KEYS array, which is laid out like Polyglot's Random64 array:
+/// 768 piece keys, then 4 castle keys, 8 en-passant keys and 1 turn key.
+const CASTLE_OFFSET: usize = 768;
+const EP_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+const KEY_COUNT: usize = 781;
+
+/// The Zobrist keys.
+const KEYS: [u64; KEY_COUNT] = make_keys();
+
+
+/// Get the hash of the given GameState, computed from scratch. `GameState::hash` keeps the same
+/// value up to date move by move.
+pub fn hash(state: &GameState) -> u64 {
+    let pieces = Square::all()
+        .filter_map(|sq| state.occupying_piece(sq).map(|piece| piece_key(piece, sq)))
+        .fold(0, |hash, piece_key| hash ^ piece_key);
+    let turn = if state.side_to_move == Color::Black { turn_key() } else { 0 };
+    pieces
+        ^ castle_rights_key(state.castlerights)
+        ^ state.ep_square.map_or(0, |sq| ep_key(sq.file()))
+        ^ turn
+}
+
+/// Get the hash of the pawns of the given GameState, computed from scratch.
+/// `GameState::pawn_hash` keeps the same value up to date move by move.
+pub fn pawn_hash(state: &GameState) -> u64 {
+    Color::all().fold(0, |hash, color| {
+        let pawn = Piece::new(color, PieceType::Pawn);
+        state.pieces(color, PieceType::Pawn)
+            .into_iter()
+            .fold(hash, |hash, sq| hash ^ piece_key(pawn, sq))
+    })
+}
+
+/// Get the key for the given piece on the given square.
+pub fn piece_key(piece: Piece, sq: Square) -> u64 {
+    KEYS[64 * piece as usize + sq.idx()]
+}
+
+/// Get the part of the hash for the given castle rights.
+pub fn castle_rights_key(castlerights: CastleRights) -> u64 {
+    (0..4)
+        .filter(|&right| castlerights.has(right))
+        .fold(0, |key, right| key ^ KEYS[CASTLE_OFFSET + right])
+}
+
+/// Get the key for an en-passant square on the given file.
+pub fn ep_key(file: File) -> u64 {
+    KEYS[EP_OFFSET + file.idx()]
+}
+
+/// Get the key for black to move.
+pub fn turn_key() -> u64 {
+    KEYS[TURN_OFFSET]
+}
+
+
+/// Make the keys with a fixed-seed SplitMix64 generator, so keys are the same on every run.
+const fn make_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
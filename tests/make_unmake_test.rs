@@ -1,7 +1,8 @@
-///! Tests the make/unmake move API offered by the GameState, Game, and GameMove structs.
-///! Put test cases in the `test_cases` function.
+//! Tests the make/unmake move API offered by the GameState, Game, and GameMove structs.
+//! Put test cases in the `test_cases` function.
 use bongcloud::game_state:: Game;
 use bongcloud::game_move::{ GameMove, MoveType };
+use bongcloud::square::Square;
 use bongcloud::fen::{ parse_fen, to_fen };
 
 
@@ -11,282 +12,492 @@ fn test_cases() -> Vec<TestCase> {
     vec![
         // White Kingside Castle
         TestCase { 
-            game_move:  GameMove::new(4, 6, MoveType::KingCastle),
+            game_move:  GameMove::new(Square::E1, Square::G1, MoveType::KingCastle),
             fen:        String::from(
-                "rnbqkbnr/ppp1pppp/8/3p4/8/4PN2/PPPP2PP/RNBQK2R w KQkq - 3 10"
+                "rnbqkbnr/ppp1pppp/8/3p4/8/4PN2/PPPP2PP/RNBQK2R w KQkq - 10 6"
             ),
             expect_fen: String::from(
-                "rnbqkbnr/ppp1pppp/8/3p4/8/4PN2/PPPP2PP/RNBQ1RK1 b kq - 3 11"
+                "rnbqkbnr/ppp1pppp/8/3p4/8/4PN2/PPPP2PP/RNBQ1RK1 b kq - 11 6"
             ),
         },
         // White Queenside Castle
         TestCase { 
-            game_move:  GameMove::new(4, 2, MoveType::QueenCastle),
+            game_move:  GameMove::new(Square::E1, Square::C1, MoveType::QueenCastle),
             fen:        String::from(
-                "r3k2r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R w KQkq - 14 8"
+                "r3k2r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R w KQkq - 8 14"
             ),
             expect_fen: String::from(
-                "r3k2r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/2KR3R b kq - 14 9"
+                "r3k2r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/2KR3R b kq - 9 14"
             ),
         }, 
         // Black Kingside Castle.
         TestCase {
-            game_move:  GameMove::new(60, 62, MoveType::KingCastle),
+            game_move:  GameMove::new(Square::E8, Square::G8, MoveType::KingCastle),
             fen:        String::from(
-                "r3k2r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R b KQkq - 14 8"
+                "r3k2r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R b KQkq - 8 14"
             ),
             expect_fen: String::from(
-                "r4rk1/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R w KQ - 15 9"
+                "r4rk1/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R w KQ - 9 15"
             ),
         },
         // Black Queenside Castle.
         TestCase { 
-            game_move:  GameMove::new(60, 58, MoveType::QueenCastle),
+            game_move:  GameMove::new(Square::E8, Square::C8, MoveType::QueenCastle),
             fen:        String::from(
-                "r3k2r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R b KQkq - 14 8"
+                "r3k2r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R b KQkq - 8 14"
             ),
             expect_fen: String::from(
-                "2kr3r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R w KQ - 15 9"
+                "2kr3r/pppqpppp/2n1b1bn/8/2B1PB2/2NP1P1N/PPP1Q1PP/R3K2R w KQ - 9 15"
             ),
         },
         // White Quiet Move.
         TestCase {
-            game_move:  GameMove::new(3, 39, MoveType::Quiet),
+            game_move:  GameMove::new(Square::D1, Square::H5, MoveType::Quiet),
             fen:        String::from(
-                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 30 5",
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 5 30",
             ),
             expect_fen: String::from(
-                "rnbqkbnr/pppp1ppp/8/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 30 6",
+                "rnbqkbnr/pppp1ppp/8/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 6 30",
             )
         },
         // Black Quiet Move.
         TestCase {
-            game_move:  GameMove::new(59, 31, MoveType::Quiet),
+            game_move:  GameMove::new(Square::D8, Square::H4, MoveType::Quiet),
             fen:        String::from(
-                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 30 5",
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 5 30",
             ),
             expect_fen: String::from(
-                "rnb1kbnr/pppp1ppp/8/4p3/4P2q/8/PPPP1PPP/RNBQKBNR w KQkq - 31 6",
+                "rnb1kbnr/pppp1ppp/8/4p3/4P2q/8/PPPP1PPP/RNBQKBNR w KQkq - 6 31",
             )
         },
         // White Double Pawn Push.
         TestCase {
-            game_move:  GameMove::new(12, 28, MoveType::DoublePawnPush),
+            game_move:  GameMove::new(Square::E2, Square::E4, MoveType::DoublePawnPush),
             fen:        String::from(
-                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 10",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 10 6",
             ),
             expect_fen: String::from(
-                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 0",
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 6",
             ),
         },
         // Black Double Pawn Push.
         TestCase {
-            game_move:  GameMove::new(52, 36, MoveType::DoublePawnPush),
+            game_move:  GameMove::new(Square::E7, Square::E5, MoveType::DoublePawnPush),
             fen:        String::from(
-                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 11",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 11 6",
             ),
             expect_fen: String::from(
-                "rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 1 0",
+                "rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 7",
                 )
         },
         // White Capture.
         TestCase {
-            game_move:  GameMove::new(21, 36, MoveType::Capture),
+            game_move:  GameMove::new(Square::F3, Square::E5, MoveType::Capture),
             fen:        String::from(
-                "rnbqkbnr/pppp1ppp/8/4p3/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 1 10"
+                "rnbqkbnr/pppp1ppp/8/4p3/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 10 6"
             ),
             expect_fen: String::from(
-                "rnbqkbnr/pppp1ppp/8/4N3/8/8/PPPPPPPP/RNBQKB1R b KQkq - 1 0"
+                "rnbqkbnr/pppp1ppp/8/4N3/8/8/PPPPPPPP/RNBQKB1R b KQkq - 0 6"
             ),
         },
         // Black Capture.
         TestCase {
-            game_move:  GameMove::new(34, 36, MoveType::Capture),
+            game_move:  GameMove::new(Square::C5, Square::E5, MoveType::Capture),
             fen:        String::from(
-                "rnb1kbnr/pppp1ppp/8/2q1N3/8/8/PPPPPPPP/RNBQKB1R b KQkq - 1 11"
+                "rnb1kbnr/pppp1ppp/8/2q1N3/8/8/PPPPPPPP/RNBQKB1R b KQkq - 11 6"
             ),
             expect_fen: String::from(
-                "rnb1kbnr/pppp1ppp/8/4q3/8/8/PPPPPPPP/RNBQKB1R w KQkq - 2 0"
+                "rnb1kbnr/pppp1ppp/8/4q3/8/8/PPPPPPPP/RNBQKB1R w KQkq - 0 7"
             ),
         },
         // White EP Capture.
         TestCase {
-            game_move:  GameMove::new(36, 43, MoveType::EpCapture),
+            game_move:  GameMove::new(Square::E5, Square::D6, MoveType::EpCapture),
             fen:        String::from(
-                "rnbqkbnr/ppp2ppp/8/3pP3/8/8/PPPP1PPP/RNBQKB1R w KQkq d6 2 10",
+                "rnbqkbnr/ppp2ppp/8/3pP3/8/8/PPPP1PPP/RNBQKB1R w KQkq d6 0 2",
             ),
             expect_fen: String::from(
-                "rnbqkbnr/ppp2ppp/3P4/8/8/8/PPPP1PPP/RNBQKB1R b KQkq - 2 0",
+                "rnbqkbnr/ppp2ppp/3P4/8/8/8/PPPP1PPP/RNBQKB1R b KQkq - 0 2",
             )
         },
         // Black EP Capture.
         TestCase {
-            game_move:  GameMove::new(29, 20, MoveType::EpCapture),
+            game_move:  GameMove::new(Square::F4, Square::E3, MoveType::EpCapture),
             fen:        String::from(
-                "rnbqkbnr/ppp3pp/8/8/4Pp2/8/PPPP1PPP/RNBQKB1R b KQkq e3 2 10",
+                "rnbqkbnr/ppp3pp/8/8/4Pp2/8/PPPP1PPP/RNBQKB1R b KQkq e3 0 2",
             ),
             expect_fen: String::from(
-                "rnbqkbnr/ppp3pp/8/8/8/4p3/PPPP1PPP/RNBQKB1R w KQkq - 3 0",
+                "rnbqkbnr/ppp3pp/8/8/8/4p3/PPPP1PPP/RNBQKB1R w KQkq - 0 3",
             )
         },
         // White Knight Promo.
         TestCase {
-            game_move:  GameMove::new(53, 61, MoveType::KnightPromo),
+            game_move:  GameMove::new(Square::F7, Square::F8, MoveType::KnightPromo),
             fen:        String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 w KQkq - 3 0",
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 w KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnbq1N2/ppp5/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 3 0",
+                "rnbq1N2/ppp5/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 0 3",
             ),
         },
         // Black Knight Promo.
         TestCase {
-            game_move:  GameMove::new(13, 5, MoveType::KnightPromo),
+            game_move:  GameMove::new(Square::F2, Square::F1, MoveType::KnightPromo),
             fen:        String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 3 0"
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 0 3"
             ),
             expect_fen: String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP4/RNBQ1n2 w KQkq - 4 0"
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP4/RNBQ1n2 w KQkq - 0 4"
             ),
         },
         // White Bishop Promo.
         TestCase {
-            game_move:  GameMove::new(53, 61, MoveType::BishopPromo),
+            game_move:  GameMove::new(Square::F7, Square::F8, MoveType::BishopPromo),
             fen:        String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 w KQkq - 3 0",
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 w KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnbq1B2/ppp5/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 3 0",
+                "rnbq1B2/ppp5/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 0 3",
             ),
         },
         // Black Bishop Promo.
         TestCase {
-            game_move:  GameMove::new(13, 5, MoveType::BishopPromo),
+            game_move:  GameMove::new(Square::F2, Square::F1, MoveType::BishopPromo),
             fen:        String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 3 0"
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 0 3"
             ),
             expect_fen: String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP4/RNBQ1b2 w KQkq - 4 0"
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP4/RNBQ1b2 w KQkq - 0 4"
             ),
         },
         // White Rook Promo.
         TestCase {
-            game_move:  GameMove::new(53, 61, MoveType::RookPromo),
+            game_move:  GameMove::new(Square::F7, Square::F8, MoveType::RookPromo),
             fen:        String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 w KQkq - 3 0",
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 w KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnbq1R2/ppp5/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 3 0",
+                "rnbq1R2/ppp5/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 0 3",
             ),
         },
         // Black Rook Promo.
         TestCase {
-            game_move:  GameMove::new(13, 5, MoveType::RookPromo),
+            game_move:  GameMove::new(Square::F2, Square::F1, MoveType::RookPromo),
             fen:        String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 3 0"
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 0 3"
             ),
             expect_fen: String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP4/RNBQ1r2 w KQkq - 4 0"
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP4/RNBQ1r2 w KQkq - 0 4"
             ),
         },
         // White Queen Promo.
         TestCase {
-            game_move:  GameMove::new(53, 61, MoveType::QueenPromo),
+            game_move:  GameMove::new(Square::F7, Square::F8, MoveType::QueenPromo),
             fen:        String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 w KQkq - 3 0",
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 w KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnbq1Q2/ppp5/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 3 0",
+                "rnbq1Q2/ppp5/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 0 3",
             ),
         },
         // Black Queen Promo.
         TestCase {
-            game_move:  GameMove::new(13, 5, MoveType::QueenPromo),
+            game_move:  GameMove::new(Square::F2, Square::F1, MoveType::QueenPromo),
             fen:        String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 3 0"
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP1p2/RNBQ4 b KQkq - 0 3"
             ),
             expect_fen: String::from(
-                "rnbq4/ppp2P2/k7/8/8/K7/PPPP4/RNBQ1q2 w KQkq - 4 0"
+                "rnbq4/ppp2P2/k7/8/8/K7/PPPP4/RNBQ1q2 w KQkq - 0 4"
             ),
         },
-        // White Knight Promo-Capture.
+        // White Knight Promo-Capture. Capturing on e8 clears black's castle rights.
         TestCase {
-            game_move:  GameMove::new(53, 60, MoveType::KnightPromoCapture),
+            game_move:  GameMove::new(Square::F7, Square::E8, MoveType::KnightPromoCapture),
             fen:        String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 3 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnb1N3/ppp5/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 3 0",
+                "rnb1N3/ppp5/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQ - 0 3",
             ),
         },
-        // Black Knight Promo-Capture.
+        // Black Knight Promo-Capture. Capturing on e1 clears white's castle rights.
         TestCase {
-            game_move:  GameMove::new(13, 4, MoveType::KnightPromoCapture),
+            game_move:  GameMove::new(Square::F2, Square::E1, MoveType::KnightPromoCapture),
             fen:        String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 3 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP4/RNB1n3 w KQkq - 4 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP4/RNB1n3 w kq - 0 4",
             ),
         },
         // White Bishop Promo-Capture.
         TestCase {
-            game_move:  GameMove::new(53, 60, MoveType::BishopPromoCapture),
+            game_move:  GameMove::new(Square::F7, Square::E8, MoveType::BishopPromoCapture),
             fen:        String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 3 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnb1B3/ppp5/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 3 0",
+                "rnb1B3/ppp5/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQ - 0 3",
             ),
         },
         // Black Bishop Promo-Capture.
         TestCase {
-            game_move:  GameMove::new(13, 4, MoveType::BishopPromoCapture),
+            game_move:  GameMove::new(Square::F2, Square::E1, MoveType::BishopPromoCapture),
             fen:        String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 3 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP4/RNB1b3 w KQkq - 4 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP4/RNB1b3 w kq - 0 4",
             ),
         },
         // White Rook Promo-Capture.
         TestCase {
-            game_move:  GameMove::new(53, 60, MoveType::RookPromoCapture),
+            game_move:  GameMove::new(Square::F7, Square::E8, MoveType::RookPromoCapture),
             fen:        String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 3 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnb1R3/ppp5/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 3 0",
+                "rnb1R3/ppp5/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQ - 0 3",
             ),
         },
         // Black Rook Promo-Capture.
         TestCase {
-            game_move:  GameMove::new(13, 4, MoveType::RookPromoCapture),
+            game_move:  GameMove::new(Square::F2, Square::E1, MoveType::RookPromoCapture),
             fen:        String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 3 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP4/RNB1r3 w KQkq - 4 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP4/RNB1r3 w kq - 0 4",
             ),
         },
         // White Queen Promo-Capture.
         TestCase {
-            game_move:  GameMove::new(53, 60, MoveType::QueenPromoCapture),
+            game_move:  GameMove::new(Square::F7, Square::E8, MoveType::QueenPromoCapture),
             fen:        String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 3 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 w KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnb1Q3/ppp5/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 3 0",
+                "rnb1Q3/ppp5/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQ - 0 3",
             ),
         },
         // Black Queen Promo-Capture.
         TestCase {
-            game_move:  GameMove::new(13, 4, MoveType::QueenPromoCapture),
+            game_move:  GameMove::new(Square::F2, Square::E1, MoveType::QueenPromoCapture),
             fen:        String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 3 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP1p2/RNB1Q3 b KQkq - 0 3",
             ),
             expect_fen: String::from(
-                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP4/RNB1q3 w KQkq - 4 0",
+                "rnb1q3/ppp2P2/k7/8/8/K7/PPPP4/RNB1q3 w kq - 0 4",
+            ),
+        },
+        // White King Move Loses Castle Rights.
+        TestCase {
+            game_move:  GameMove::new(Square::E1, Square::F1, MoveType::Quiet),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"
+            ),
+            expect_fen: String::from(
+                "r3k2r/8/8/8/8/8/8/R4K1R b kq - 1 1"
+            ),
+        },
+        // Black King Move Loses Castle Rights.
+        TestCase {
+            game_move:  GameMove::new(Square::E8, Square::D8, MoveType::Quiet),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1"
+            ),
+            expect_fen: String::from(
+                "r2k3r/8/8/8/8/8/8/R3K2R w KQ - 1 2"
+            ),
+        },
+        // White King Capture Loses Castle Rights.
+        TestCase {
+            game_move:  GameMove::new(Square::E1, Square::D2, MoveType::Capture),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/3p4/R3K2R w KQkq - 5 4"
+            ),
+            expect_fen: String::from(
+                "r3k2r/8/8/8/8/8/3K4/R6R b kq - 0 4"
+            ),
+        },
+        // White Kingside Rook Move.
+        TestCase {
+            game_move:  GameMove::new(Square::H1, Square::G1, MoveType::Quiet),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"
+            ),
+            expect_fen: String::from(
+                "r3k2r/8/8/8/8/8/8/R3K1R1 b Qkq - 1 1"
+            ),
+        },
+        // White Queenside Rook Move.
+        TestCase {
+            game_move:  GameMove::new(Square::A1, Square::B1, MoveType::Quiet),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"
+            ),
+            expect_fen: String::from(
+                "r3k2r/8/8/8/8/8/8/1R2K2R b Kkq - 1 1"
+            ),
+        },
+        // Black Kingside Rook Move.
+        TestCase {
+            game_move:  GameMove::new(Square::H8, Square::G8, MoveType::Quiet),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1"
+            ),
+            expect_fen: String::from(
+                "r3k1r1/8/8/8/8/8/8/R3K2R w KQq - 1 2"
+            ),
+        },
+        // Black Queenside Rook Move.
+        TestCase {
+            game_move:  GameMove::new(Square::A8, Square::B8, MoveType::Quiet),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1"
+            ),
+            expect_fen: String::from(
+                "1r2k2r/8/8/8/8/8/8/R3K2R w KQk - 1 2"
+            ),
+        },
+        // White Kingside Rook Move With Only Kingside Rights.
+        TestCase {
+            game_move:  GameMove::new(Square::H1, Square::G1, MoveType::Quiet),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 0 1"
+            ),
+            expect_fen: String::from(
+                "r3k2r/8/8/8/8/8/8/R3K1R1 b kq - 1 1"
+            ),
+        },
+        // White Rook Move Losing Last Castle Rights.
+        TestCase {
+            game_move:  GameMove::new(Square::H1, Square::G1, MoveType::Quiet),
+            fen:        String::from(
+                "4k3/8/8/8/8/8/8/4K2R w K - 0 1"
+            ),
+            expect_fen: String::from(
+                "4k3/8/8/8/8/8/8/4K1R1 b - - 1 1"
+            ),
+        },
+        // Rook Move Off Home Square Keeps Castle Rights.
+        TestCase {
+            game_move:  GameMove::new(Square::A2, Square::A3, MoveType::Quiet),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/R7/4K2R w Kkq - 0 1"
+            ),
+            expect_fen: String::from(
+                "r3k2r/8/8/8/8/R7/8/4K2R b Kkq - 1 1"
+            ),
+        },
+        // White Rook Captures Rook On h8.
+        TestCase {
+            game_move:  GameMove::new(Square::H1, Square::H8, MoveType::Capture),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 5 4"
+            ),
+            expect_fen: String::from(
+                "r3k2R/8/8/8/8/8/8/R3K3 b Qq - 0 4"
+            ),
+        },
+        // White Rook Captures Rook On a8.
+        TestCase {
+            game_move:  GameMove::new(Square::A1, Square::A8, MoveType::Capture),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 5 4"
+            ),
+            expect_fen: String::from(
+                "R3k2r/8/8/8/8/8/8/4K2R b Kk - 0 4"
+            ),
+        },
+        // Black Rook Captures Rook On h1.
+        TestCase {
+            game_move:  GameMove::new(Square::H8, Square::H1, MoveType::Capture),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 5 3"
+            ),
+            expect_fen: String::from(
+                "r3k3/8/8/8/8/8/8/R3K2r w Qq - 0 4"
+            ),
+        },
+        // Black Rook Captures Rook On a1.
+        TestCase {
+            game_move:  GameMove::new(Square::A8, Square::A1, MoveType::Capture),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 5 3"
+            ),
+            expect_fen: String::from(
+                "4k2r/8/8/8/8/8/8/r3K2R w Kk - 0 4"
+            ),
+        },
+        // White Knight Captures Rook On h8.
+        TestCase {
+            game_move:  GameMove::new(Square::G6, Square::H8, MoveType::Capture),
+            fen:        String::from(
+                "r3k2r/8/6N1/8/8/8/8/R3K2R w KQkq - 5 4"
+            ),
+            expect_fen: String::from(
+                "r3k2N/8/8/8/8/8/8/R3K2R b KQq - 0 4"
+            ),
+        },
+        // White Promo-Capture On a8.
+        TestCase {
+            game_move:  GameMove::new(Square::B7, Square::A8, MoveType::QueenPromoCapture),
+            fen:        String::from(
+                "r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 5 4"
+            ),
+            expect_fen: String::from(
+                "Q3k2r/8/8/8/8/8/8/R3K2R b KQk - 0 4"
+            ),
+        },
+        // Black Promo-Capture On h1.
+        TestCase {
+            game_move:  GameMove::new(Square::G2, Square::H1, MoveType::QueenPromoCapture),
+            fen:        String::from(
+                "r3k2r/8/8/8/8/8/6p1/R3K2R b KQkq - 5 3"
+            ),
+            expect_fen: String::from(
+                "r3k2r/8/8/8/8/8/8/R3K2q w Qkq - 0 4"
+            ),
+        },
+        // Chess960 White Kingside Castle With The Rook On The King's Destination.
+        TestCase {
+            game_move:  GameMove::new(Square::F1, Square::G1, MoveType::KingCastle),
+            fen:        String::from(
+                "5kr1/8/8/8/8/8/8/5KR1 w Kk - 0 1"
+            ),
+            expect_fen: String::from(
+                "5kr1/8/8/8/8/8/8/5RK1 b k - 1 1"
+            ),
+        },
+        // Chess960 White Queenside Castle From b1.
+        TestCase {
+            game_move:  GameMove::new(Square::B1, Square::C1, MoveType::QueenCastle),
+            fen:        String::from(
+                "1k6/8/8/8/8/8/8/RK6 w Q - 0 1"
+            ),
+            expect_fen: String::from(
+                "1k6/8/8/8/8/8/8/2KR4 b - - 1 1"
+            ),
+        },
+        // Chess960 Black Kingside Castle With The Inner Rook, Which Doesn't Move.
+        TestCase {
+            game_move:  GameMove::new(Square::D8, Square::G8, MoveType::KingCastle),
+            fen:        String::from(
+                "3k1r1r/8/8/8/8/8/8/4K3 b f - 0 1"
+            ),
+            expect_fen: String::from(
+                "5rkr/8/8/8/8/8/8/4K3 w - - 1 2"
+            ),
+        },
+        // Chess960 Castling Rook Move Loses Castle Rights.
+        TestCase {
+            game_move:  GameMove::new(Square::G1, Square::G2, MoveType::Quiet),
+            fen:        String::from(
+                "5kr1/8/8/8/8/8/8/5KR1 w Kk - 0 1"
+            ),
+            expect_fen: String::from(
+                "5kr1/8/8/8/8/8/6R1/5K2 b k - 1 1"
             ),
         },
     ]
@@ -308,9 +519,53 @@ pub fn run_test_cases() {
         let mut game = Game::new(parse_fen(&test_case.fen));
         assert_eq!(&to_fen(&game.current_state()), &test_case.fen);
 
+        let copied = game.current_state().make_copy(test_case.game_move);
+        assert_eq!(&to_fen(&copied), &test_case.expect_fen);
+
         game.make(test_case.game_move);
         assert_eq!(&to_fen(&game.current_state()), &test_case.expect_fen);
-        game.unmake();
+        game.unmake().unwrap();
         assert_eq!(&to_fen(&game.current_state()), &test_case.fen);
+        assert_eq!(game.current_state().validate_board(), Ok(()));
+    }
+}
+
+
+/// Play a game longer than the fast region of the undo stack, then unmake back to the start.
+#[test]
+pub fn long_game_unmakes_to_start() {
+    let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    let mut game = Game::new(parse_fen(fen));
+    assert!(game.unmake().is_err());
+
+    let knight_shuffle = [
+        GameMove::new(Square::G1, Square::F3, MoveType::Quiet),
+        GameMove::new(Square::G8, Square::F6, MoveType::Quiet),
+        GameMove::new(Square::F3, Square::G1, MoveType::Quiet),
+        GameMove::new(Square::F6, Square::G8, MoveType::Quiet),
+    ];
+    let mut fens = vec![to_fen(&game.current_state())];
+    for file in 0..5 {
+        // Push a pawn for each side so the halfmove clock is reset.
+        game.make(GameMove::new(Square::new(8 + file), Square::new(16 + file), MoveType::Quiet));
+        fens.push(to_fen(&game.current_state()));
+        game.make(GameMove::new(Square::new(48 + file), Square::new(40 + file), MoveType::Quiet));
+        fens.push(to_fen(&game.current_state()));
+
+        for _ in 0..15 {
+            for knight_move in knight_shuffle {
+                game.make(knight_move);
+                fens.push(to_fen(&game.current_state()));
+            }
+        }
+    }
+    assert_eq!(game.depth_from_start(), 310);
+
+    fens.pop();
+    while let Some(expect_fen) = fens.pop() {
+        game.unmake().unwrap();
+        assert_eq!(to_fen(&game.current_state()), expect_fen);
     }
+    assert_eq!(game.depth_from_start(), 0);
+    assert!(game.unmake().is_err());
 }